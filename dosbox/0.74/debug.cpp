@@ -28,6 +28,7 @@
 #include <iomanip>
 #include <string>
 #include <sstream>
+#include <vector>
 using namespace std;
 
 #include <dbus/dbus.h>
@@ -44,6 +45,7 @@ using namespace std;
 #include "mixer.h"
 #include "timer.h"
 #include "paging.h"
+#include "fpu.h"
 #include "support.h"
 #include "shell.h"
 #include "programs.h"
@@ -120,6 +122,47 @@ Bit32u GetAddress(Bit16u seg, Bit32u offset)
 	return (seg<<4)+offset;
 }
 
+// Modes of the `read` and `write` memory methods
+enum MemMode
+{
+	MEM_REAL,		// segment:offset of real mode
+	MEM_PROT,		// selector:offset
+	MEM_LINEAR,		// linear offset, paging applies
+	MEM_PHYSICAL	// physical offset
+};
+
+// Linear (or physical) address of the memory `mode` says, false for a selector not in GDT or LDT
+bool GetModeAddress(uint8_t mode, Bit16u segment, Bit32u offset, PhysPt& addr)
+{
+	Descriptor desc;
+
+	switch(mode)
+	{
+		case MEM_REAL:
+			addr = (segment << 4) + offset;
+			return true;
+		case MEM_PROT:
+			if(!cpu.gdt.GetDescriptor(segment, desc)) return false;
+			addr = desc.GetBase() + offset;
+			return true;
+		case MEM_LINEAR:
+		case MEM_PHYSICAL:
+			addr = offset;
+			return true;
+	}
+
+	return false;
+}
+
+bool ReadModeByte(uint8_t mode, PhysPt addr, uint8_t* b)
+{
+	if(mode != MEM_PHYSICAL) return !mem_readb_checked(addr, b);
+	if(addr >= MEM_TotalPages() * 4096) return false;
+
+	*b = phys_readb(addr);
+	return true;
+}
+
 static char empty_sel[] = { ' ',' ',0 };
 
 bool GetDescriptorInfo(char* selname, char* out1, char* out2)
@@ -209,7 +252,7 @@ public:
 	// statics
 	static CBreakpoint*		AddBreakpoint		(Bit16u seg, Bit32u off, bool once);
 	static CBreakpoint*		AddIntBreakpoint	(Bit8u intNum, Bit16u ah, bool once);
-	static CBreakpoint*		AddMemBreakpoint	(Bit16u seg, Bit32u off);
+	static CBreakpoint*		AddMemBreakpoint	(Bit16u seg, Bit32u off, EBreakpoint type = BKPNT_MEMORY);
 	static void				ActivateBreakpoints	(PhysPt adr, bool activate);
 	static bool				CheckBreakpoint		(PhysPt adr);
 	static bool				CheckBreakpoint		(Bitu seg, Bitu off);
@@ -217,6 +260,8 @@ public:
 	static bool				IsBreakpoint		(PhysPt where);
 	static bool				IsBreakpointDrawn	(PhysPt where);
 	static bool				DeleteBreakpoint	(PhysPt where);
+	static bool				DeleteIntBreakpoint	(Bit8u intNum, Bit16u ah);
+	static bool				DeleteMemBreakpoint	(Bit16u seg, Bit32u off, EBreakpoint type);
 	static bool				DeleteByIndex		(Bit16u index);
 	static void				DeleteAll			(void);
 	static void				ShowList			(void);
@@ -292,12 +337,16 @@ CBreakpoint* CBreakpoint::AddIntBreakpoint(Bit8u intNum, Bit16u ah, bool once)
 	return bp;
 };
 
-CBreakpoint* CBreakpoint::AddMemBreakpoint(Bit16u seg, Bit32u off)
+CBreakpoint* CBreakpoint::AddMemBreakpoint(Bit16u seg, Bit32u off, EBreakpoint type)
 {
 	CBreakpoint* bp = new CBreakpoint();
 	bp->SetAddress		(seg,off);
 	bp->SetOnce			(false);
-	bp->SetType			(BKPNT_MEMORY);
+	bp->SetType			(type);
+	// Watch for changes of the current value
+	Bit8u value = 0;
+	mem_readb_checked((type==BKPNT_MEMORY_LINEAR) ? off : GetAddress(seg,off),&value);
+	bp->SetValue		(value);
 	BPoints.push_front	(bp);
 	return bp;
 };
@@ -309,20 +358,42 @@ void CBreakpoint::ActivateBreakpoints(PhysPt adr, bool activate)
 	CBreakpoint* bp;
 	for(i=BPoints.begin(); i != BPoints.end(); i++) {
 		bp = (*i);
+#if !C_HEAVY_DEBUG
 		// Do not activate, when bp is an actual adress
 		if (activate && (bp->GetType()==BKPNT_PHYSICAL) && (bp->GetLocation()==adr)) {
 			// Do not activate :)
 			continue;
 		}
+#endif
 		bp->Activate(activate);	
 	};
 };
 
+// Last memory breakpoint that stopped execution, for the watch_hit method
+struct WatchHit {
+	dbus_bool_t	valid;
+	EBreakpoint	type;
+	Bit16u		segment;
+	Bit32u		offset;
+	Bit8u		oldValue;
+	Bit8u		newValue;
+	// Instruction executed before the stop, the one that wrote the value
+	Bit16u		cs;
+	Bit32u		eip;
+};
+
+static WatchHit	_watchHit;
+static Bit16u	_lastCs = 0;
+static Bit32u	_lastEip = 0;
+
 bool CBreakpoint::CheckBreakpoint(Bitu seg, Bitu off)
 // Checks if breakpoint is valid an should stop execution
 {
 	if ((ignoreAddressOnce!=0) && (GetAddress(seg,off)==ignoreAddressOnce)) {
-		ignoreAddressOnce = 0;
+		// The skip replaces the one of the last stop. Without a breakpoint here
+		// it's left for an int breakpoint on the same instruction.
+		if (IsBreakpoint(ignoreAddressOnce)) ignoreAddressOnce = 0;
+		ignoreOnce = 0;
 		return false;
 	} else
 		ignoreAddressOnce = 0;
@@ -372,6 +443,14 @@ bool CBreakpoint::CheckBreakpoint(Bitu seg, Bitu off)
 				if (bp->GetValue() != value) {
 					// Yup, memory value changed
 //					DEBUG_ShowMsg("DEBUG: Memory breakpoint %s: %04X:%04X - %02X -> %02X\n",(bp->GetType()==BKPNT_MEMORY_PROT)?"(Prot)":"",bp->GetSegment(),bp->GetOffset(),bp->GetValue(),value);
+					_watchHit.valid		= true;
+					_watchHit.type		= bp->GetType();
+					_watchHit.segment	= bp->GetSegment();
+					_watchHit.offset	= bp->GetOffset();
+					_watchHit.oldValue	= (Bit8u)bp->GetValue();
+					_watchHit.newValue	= value;
+					_watchHit.cs		= _lastCs;
+					_watchHit.eip		= _lastEip;
 					bp->SetValue(value);
 					return true;
 				};		
@@ -387,6 +466,7 @@ bool CBreakpoint::CheckIntBreakpoint(PhysPt adr, Bit8u intNr, Bit16u ahValue)
 {
 	if ((ignoreAddressOnce!=0) && (adr==ignoreAddressOnce)) {
 		ignoreAddressOnce = 0;
+		ignoreOnce = 0;
 		return false;
 	} else
 		ignoreAddressOnce = 0;
@@ -469,6 +549,40 @@ bool CBreakpoint::DeleteBreakpoint(PhysPt where)
 	return false;
 };
 
+bool CBreakpoint::DeleteIntBreakpoint(Bit8u intNum, Bit16u ah)
+{
+	// Search matching breakpoint
+	std::list<CBreakpoint*>::iterator i;
+	CBreakpoint* bp;
+	for(i=BPoints.begin(); i != BPoints.end(); i++) {
+		bp = (*i);
+		if ((bp->GetType()==BKPNT_INTERRUPT) && (bp->GetIntNr()==intNum) && (bp->GetValue()==ah)) {
+			(BPoints.erase)(i);
+			bp->Activate(false);
+			delete bp;
+			return true;
+		}
+	};
+	return false;
+};
+
+bool CBreakpoint::DeleteMemBreakpoint(Bit16u seg, Bit32u off, EBreakpoint type)
+{
+	// Search matching breakpoint
+	std::list<CBreakpoint*>::iterator i;
+	CBreakpoint* bp;
+	for(i=BPoints.begin(); i != BPoints.end(); i++) {
+		bp = (*i);
+		if ((bp->GetType()==type) && (bp->GetSegment()==seg) && (bp->GetOffset()==off)) {
+			(BPoints.erase)(i);
+			bp->Activate(false);
+			delete bp;
+			return true;
+		}
+	};
+	return false;
+};
+
 bool CBreakpoint::IsBreakpoint(PhysPt adr) 
 // is there a breakpoint at address ?
 {
@@ -529,6 +643,7 @@ void CBreakpoint::ShowList(void)
 
 Bitu _ret = 0;
 Bit32u _skipEip = 0;
+bool _breakIn = false;
 
 
 void break_reply(DBusPendingCall* pcall, void* user_data)
@@ -565,11 +680,29 @@ void break_reply(DBusPendingCall* pcall, void* user_data)
 }
 
 
+void notify_break()
+{
+	DBusError err;
+	dbus_error_init(&err);
+
+	DBusConnection* session = dbus_bus_get(DBUS_BUS_SESSION, &err);
+	DBusMessage* msg = dbus_message_new_method_call("com.dosbox.dbg", "/", "com.dosbox.dbg", "breakNow");
+
+	DBusPendingCall* pcall;
+	dbus_connection_send_with_reply(session, msg, &pcall, -1);
+	dbus_pending_call_set_notify(pcall, break_reply, NULL, NULL);
+
+	dbus_message_unref(msg);
+	dbus_connection_unref(session);
+}
+
+
 bool DEBUG_Breakpoint()
 {
 	if(CBreakpoint::CheckBreakpoint(SegValue(cs), reg_eip))
 	{
 		CBreakpoint::ActivateBreakpoints(GetAddress(SegValue(cs), reg_eip), false);
+		notify_break();
 
 		return true;
 	}
@@ -598,18 +731,7 @@ bool DEBUG_Breakpoint()
 		return false;
 	}
 
-	DBusError err;
-	dbus_error_init(&err);
-
-	DBusConnection* session = dbus_bus_get(DBUS_BUS_SESSION, &err);
-	DBusMessage* msg = dbus_message_new_method_call("com.dosbox.dbg", "/", "com.dosbox.dbg", "breakNow");
-
-	DBusPendingCall* pcall;
-	dbus_connection_send_with_reply(session, msg, &pcall, -1);
-	dbus_pending_call_set_notify(pcall, break_reply, NULL, NULL);
-
-	dbus_message_unref(msg);
-	dbus_connection_unref(session);
+	notify_break();
 
 	return true;
 
@@ -663,6 +785,7 @@ bool DEBUG_IntBreakpoint(Bit8u intNum)
 	if (!CBreakpoint::CheckIntBreakpoint(where,intNum,reg_ah)) return false;
 	// Found. Breakpoint is valid
 	CBreakpoint::ActivateBreakpoints(where,false);	// Deactivate all breakpoints
+	notify_break();
 	return true;
 };
 
@@ -688,6 +811,12 @@ Bitu DEBUG_Loop(void)
 	Bit32u oldEIP	= reg_eip;
 	PIC_runIRQs();
 
+	if(_breakIn)
+	{
+		_breakIn = false;
+		notify_break();
+	}
+
 	dbus_connection_read_write_dispatch(_conn, -1);
 
 	/*SDL_Delay(1);
@@ -706,6 +835,15 @@ Bitu DEBUG_Loop(void)
 	return _ret;
 }
 
+// serves D-Bus while the CPU runs, DEBUG_Loop takes over once it stops
+static void DEBUG_PollDBus(Bitu /*val*/)
+{
+	if(!debugging)
+		dbus_connection_read_write_dispatch(_conn, 0);
+
+	PIC_AddEvent(DEBUG_PollDBus, 10.0f);
+}
+
 void DEBUG_Enable(bool pressed) {
 	if (!pressed)
 		return;
@@ -867,6 +1005,29 @@ DBusHandlerResult dbg_handler(DBusConnection* conn, DBusMessage* msg, void* data
 }
 
 
+// Executes the instruction at CS:EIP, a breakpoint there is skipped.
+static void StepIn()
+{
+	exitLoop = false;
+	CPU_Cycles = 1;
+	CBreakpoint::ignoreOnce = 0;
+	ignoreAddressOnce = SegPhys(cs) + reg_eip;
+	_watchHit.valid = false;
+
+	Bits r = (*cpudecoder)();
+
+	if(r > 0)
+	{
+		_ret = (*CallBack_Handlers[r])();
+
+		if(_ret)
+		{
+			exitLoop = true;
+			CPU_Cycles = CPU_CycleLeft = 0;
+		}
+	}
+}
+
 DBusHandlerResult cpu_handler(DBusConnection* conn, DBusMessage* msg, void* data)
 {
 	DBusHandlerResult ret = DBUS_HANDLER_RESULT_NOT_YET_HANDLED;
@@ -877,8 +1038,41 @@ DBusHandlerResult cpu_handler(DBusConnection* conn, DBusMessage* msg, void* data
 		DBusMessageIter ri;
 		dbus_message_iter_init_append(rm, &ri);
 
-		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &cpu.pmode);
-		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &cpu.code.big);
+		dbus_bool_t f;
+
+		f = cpu.pmode;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &f);
+		f = cpu.code.big;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &f);
+		f = cpu.stack.big;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &f);
+		f = (reg_flags & FLAG_VM) != 0;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &f);
+
+		if(!dbus_connection_send(conn, rm, NULL))
+			E_Exit("dbus error: dbus_connection_send failed");
+
+		dbus_connection_flush(conn);
+		dbus_message_unref(rm);
+
+		ret = DBUS_HANDLER_RESULT_HANDLED;
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "control"))
+	{
+		DBusMessage* rm = dbus_message_new_method_return(msg);
+		DBusMessageIter ri;
+		dbus_message_iter_init_append(rm, &ri);
+
+		uint32_t cr;
+
+		cr = (uint32_t)cpu.cr0;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &cr);
+		cr = (uint32_t)paging.cr2;
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &cr);
+		cr = (uint32_t)PAGING_GetDirBase();
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &cr);
+		cr = 0; // DOSBox keeps no CR4, none of its features are emulated
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &cr);
 
 		if(!dbus_connection_send(conn, rm, NULL))
 			E_Exit("dbus error: dbus_connection_send failed");
@@ -924,24 +1118,22 @@ DBusHandlerResult cpu_handler(DBusConnection* conn, DBusMessage* msg, void* data
 	}
 	else
 	{
-		if(dbus_message_is_method_call(msg, "com.dosbox", "step_in"))
+		if(!debugging)
 		{
-			exitLoop = false;
-			CPU_Cycles = 1;
-			CBreakpoint::ignoreOnce = 0;
-
-			Bits r = (*cpudecoder)();
-
-			if(r > 0)
+			if(dbus_message_is_method_call(msg, "com.dosbox", "break"))
 			{
-				_ret = (*CallBack_Handlers[r])();
+				// same as DEBUG_EnableDebugger, the stop is announced from DEBUG_Loop
+				_breakIn = true;
+				exitLoop = true;
+				DEBUG_Enable(true);
+				CPU_Cycles = CPU_CycleLeft = 0;
 
-				if(_ret)
-				{
-					exitLoop = true;
-					CPU_Cycles = CPU_CycleLeft = 0;
-				}
+				ret = DBUS_HANDLER_RESULT_HANDLED;
 			}
+		}
+		else if(dbus_message_is_method_call(msg, "com.dosbox", "step_in"))
+		{
+			StepIn();
 
 			ret = DBUS_HANDLER_RESULT_HANDLED;
 		}
@@ -958,16 +1150,50 @@ DBusHandlerResult cpu_handler(DBusConnection* conn, DBusMessage* msg, void* data
 			ret = DBUS_HANDLER_RESULT_HANDLED;
 */
 			debugging = false;
+
+#if C_HEAVY_DEBUG
+			// breakpoints are checked rather than patched in, so the one the CPU stands on
+			// is armed too and only the current instruction is skipped
+			CBreakpoint::ActivateBreakpoints(SegPhys(cs) + reg_eip, true);
+			ignoreAddressOnce = SegPhys(cs) + reg_eip;
+#else
+			// a 0xCC can't be written under the CPU, get off the breakpoint first
+			StepIn();
+			CBreakpoint::ActivateBreakpoints(SegPhys(cs) + reg_eip, true);
+#endif
+			_watchHit.valid = false;
+
 			DOSBOX_SetNormalLoop();
 
 			ret = DBUS_HANDLER_RESULT_HANDLED;
 		}
+		else if(dbus_message_is_method_call(msg, "com.dosbox", "break"))
+		{
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
 
-		DBusMessage* rm = dbus_message_new_method_return(msg);
-		DBusMessageIter ri;
-		dbus_message_iter_init_append(rm, &ri);
+		DBusMessage* rm;
 
-		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &reg_eip);
+		if(ret == DBUS_HANDLER_RESULT_HANDLED)
+		{
+			rm = dbus_message_new_method_return(msg);
+			DBusMessageIter ri;
+			dbus_message_iter_init_append(rm, &ri);
+
+			dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &reg_eip);
+		}
+		// nothing has happened, so no EIP either
+		else if(!debugging && (dbus_message_is_method_call(msg, "com.dosbox", "step_in")
+			|| dbus_message_is_method_call(msg, "com.dosbox", "run")))
+		{
+			rm = dbus_message_new_error(msg, DBUS_ERROR_FAILED, "The CPU is running.");
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
+		else
+		{
+			rm = dbus_message_new_error(msg, DBUS_ERROR_UNKNOWN_METHOD, "Unknown method.");
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
 
 		if(!dbus_connection_send(conn, rm, NULL))
 			E_Exit("dbus error: dbus_connection_send failed");
@@ -1116,6 +1342,75 @@ DBusHandlerResult cpu_regs_handler(DBusConnection* conn, DBusMessage* msg, void*
 
 		ret = DBUS_HANDLER_RESULT_HANDLED;
 	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "set"))
+	{
+		DBusError err;
+		dbus_error_init(&err);
+
+		const char* name;
+		uint32_t value;
+
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_STRING, &name,
+			DBUS_TYPE_UINT32, &value,
+			DBUS_TYPE_INVALID))
+		{
+			dbus_error_free(&err);
+			dbus_free_string_array(path);
+			return ret;
+		}
+
+		std::string reg(name);
+		dbus_bool_t done = true;
+
+		// flags may still be lazy, get them in place before changing any
+		FillFlags();
+
+		if(reg == "eax") reg_eax = value;
+		else if(reg == "ebx") reg_ebx = value;
+		else if(reg == "ecx") reg_ecx = value;
+		else if(reg == "edx") reg_edx = value;
+		else if(reg == "esi") reg_esi = value;
+		else if(reg == "edi") reg_edi = value;
+		else if(reg == "ebp") reg_ebp = value;
+		else if(reg == "esp") reg_esp = value;
+		else if(reg == "eip") reg_eip = value;
+		else if(reg == "efl") reg_flags = value;
+		else if(reg == "cs") SegSet16(cs, value);
+		else if(reg == "ds") SegSet16(ds, value);
+		else if(reg == "es") SegSet16(es, value);
+		else if(reg == "fs") SegSet16(fs, value);
+		else if(reg == "gs") SegSet16(gs, value);
+		else if(reg == "ss") SegSet16(ss, value);
+		else if(reg == "cf") SETFLAGBIT(CF, value);
+		else if(reg == "pf") SETFLAGBIT(PF, value);
+		else if(reg == "af") SETFLAGBIT(AF, value);
+		else if(reg == "zf") SETFLAGBIT(ZF, value);
+		else if(reg == "sf") SETFLAGBIT(SF, value);
+		else if(reg == "tf") SETFLAGBIT(TF, value);
+		else if(reg == "if") SETFLAGBIT(IF, value);
+		else if(reg == "df") SETFLAGBIT(DF, value);
+		else if(reg == "of") SETFLAGBIT(OF, value);
+		else if(reg == "iopl") reg_flags = (reg_flags & ~FLAG_IOPL) | ((value & 3) << 12);
+		else if(reg == "nt") SETFLAGBIT(NT, value);
+		else if(reg == "vm") SETFLAGBIT(VM, value);
+		else if(reg == "ac") SETFLAGBIT(AC, value);
+		else if(reg == "id") SETFLAGBIT(ID, value);
+		else done = false;
+
+		DBusMessage* rm = dbus_message_new_method_return(msg);
+		DBusMessageIter ri;
+		dbus_message_iter_init_append(rm, &ri);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &done);
+
+		if(!dbus_connection_send(conn, rm, NULL))
+			E_Exit("dbus error: dbus_connection_send failed");
+
+		dbus_connection_flush(conn);
+		dbus_message_unref(rm);
+
+		ret = DBUS_HANDLER_RESULT_HANDLED;
+	}
 
 	dbus_free_string_array(path);
 
@@ -1347,9 +1642,504 @@ DBusHandlerResult mem_handler(DBusConnection* conn, DBusMessage* msg, void* data
 			}
 		}
 	}
+	// `read` and `write` take the mode explicitly instead of guessing like GetAddress
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "read"))
+	{
+		DBusError err;
+		dbus_error_init(&err);
+
+		uint8_t mode;
+		uint16_t segment;
+		uint32_t offset;
+		uint32_t length;
+
+		if(dbus_message_get_args(msg, &err,
+			DBUS_TYPE_BYTE, &mode,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_UINT32, &length,
+			DBUS_TYPE_INVALID))
+		{
+			PhysPt start;
+			DBusMessage* rm;
+
+			// no pane reads more, and a selector can't reach further anyway
+			if(length > 0x10000) length = 0x10000;
+
+			vector<uint8_t> data(length);
+			uint32_t count = 0;
+
+			if(!GetModeAddress(mode, segment, offset, start))
+			{
+				rm = dbus_message_new_error(msg, DBUS_ERROR_INVALID_ARGS, "Selector isn't in the GDT or LDT.");
+			}
+			else
+			{
+				while(count < length && ReadModeByte(mode, start + count, &data[count]))
+					++count;
+
+				if(count < length)
+				{
+					char text[64];
+					snprintf(text, sizeof(text), "Memory at %08X isn't readable.", (uint32_t)(start + count));
+
+					rm = dbus_message_new_error(msg, DBUS_ERROR_FAILED, text);
+				}
+				else
+				{
+					rm = dbus_message_new_method_return(msg);
+					DBusMessageIter ri;
+					dbus_message_iter_init_append(rm, &ri);
+
+					DBusMessageIter bytes;
+					dbus_message_iter_open_container(&ri, DBUS_TYPE_ARRAY, DBUS_TYPE_BYTE_AS_STRING, &bytes);
+
+					for(uint32_t i = 0; i < length; ++i)
+						dbus_message_iter_append_basic(&bytes, DBUS_TYPE_BYTE, &data[i]);
+
+					dbus_message_iter_close_container(&ri, &bytes);
+				}
+			}
+
+			if(!dbus_connection_send(conn, rm, NULL))
+				E_Exit("dbus error: dbus_connection_send failed");
+
+			dbus_connection_flush(conn);
+			dbus_message_unref(rm);
+
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
+		else
+			dbus_error_free(&err);
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "write"))
+	{
+		DBusError err;
+		dbus_error_init(&err);
+
+		uint8_t mode;
+		uint16_t segment;
+		uint32_t offset;
+		uint8_t b;
+
+		if(dbus_message_get_args(msg, &err,
+			DBUS_TYPE_BYTE, &mode,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_BYTE, &b,
+			DBUS_TYPE_INVALID))
+		{
+			PhysPt addr;
+			DBusMessage* rm;
+
+			if(!GetModeAddress(mode, segment, offset, addr))
+			{
+				rm = dbus_message_new_error(msg, DBUS_ERROR_INVALID_ARGS, "Selector isn't in the GDT or LDT.");
+			}
+			else
+			{
+				rm = dbus_message_new_method_return(msg);
+				DBusMessageIter ri;
+				dbus_message_iter_init_append(rm, &ri);
+
+				uint8_t tmp;
+				if(!ReadModeByte(mode, addr, &tmp)) tmp = 0;
+
+				dbus_message_iter_append_basic(&ri, DBUS_TYPE_BYTE, &tmp);
+
+				if(mode != MEM_PHYSICAL)
+					mem_writeb_checked(addr, b);
+				else if(addr < MEM_TotalPages() * 4096)
+					phys_writeb(addr, b);
+			}
+
+			if(!dbus_connection_send(conn, rm, NULL))
+				E_Exit("dbus error: dbus_connection_send failed");
+
+			dbus_connection_flush(conn);
+			dbus_message_unref(rm);
+
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
+		else
+			dbus_error_free(&err);
+	}
+
+	return ret;
+}
+
+DBusHandlerResult desc_handler(DBusConnection* conn, DBusMessage* msg, void* data)
+{
+	DBusHandlerResult ret = DBUS_HANDLER_RESULT_NOT_YET_HANDLED;
+
+	if(dbus_message_is_method_call(msg, "com.dosbox", "get"))
+	{
+		DBusError err;
+		dbus_error_init(&err);
+
+		uint8_t table;
+
+		if(dbus_message_get_args(msg, &err,
+			DBUS_TYPE_BYTE, &table,
+			DBUS_TYPE_INVALID))
+		{
+			uint32_t base = 0;
+			Bitu length = 0;
+			Descriptor desc;
+
+			switch(table)
+			{
+				case 0: // GDT
+					if(cpu.pmode)
+					{
+						base = cpu.gdt.GetBase();
+						length = cpu.gdt.GetLimit() + 1;
+					}
+					break;
+				case 1: // LDT
+					if(cpu.pmode && cpu.gdt.SLDT() && cpu.gdt.GetDescriptor(cpu.gdt.SLDT(), desc))
+					{
+						base = desc.GetBase();
+						length = desc.GetLimit() + 1;
+					}
+					break;
+				case 2: // IDT, the interrupt vector table in real mode
+					base = cpu.idt.GetBase();
+					length = cpu.idt.GetLimit() + 1;
+					break;
+			}
+
+			// a selector can't reach further anyway
+			if(length > 0x10000) length = 0x10000;
+
+			DBusMessage* rm = dbus_message_new_method_return(msg);
+			DBusMessageIter ri;
+			dbus_message_iter_init_append(rm, &ri);
+
+			dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &base);
+
+			DBusMessageIter bytes;
+			dbus_message_iter_open_container(&ri, DBUS_TYPE_ARRAY, DBUS_TYPE_BYTE_AS_STRING, &bytes);
+
+			for(Bitu i = 0; i < length; ++i)
+			{
+				uint8_t b;
+				if(mem_readb_checked(base + i, &b)) b = 0;
+
+				dbus_message_iter_append_basic(&bytes, DBUS_TYPE_BYTE, &b);
+			}
+
+			dbus_message_iter_close_container(&ri, &bytes);
+
+			if(!dbus_connection_send(conn, rm, NULL))
+				E_Exit("dbus error: dbus_connection_send failed");
+
+			dbus_connection_flush(conn);
+			dbus_message_unref(rm);
+
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
+		else
+			dbus_error_free(&err);
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "lookup"))
+	{
+		DBusError err;
+		dbus_error_init(&err);
+
+		uint16_t selector;
+
+		if(dbus_message_get_args(msg, &err,
+			DBUS_TYPE_UINT16, &selector,
+			DBUS_TYPE_INVALID))
+		{
+			Descriptor desc;
+			DBusMessage* rm;
+
+			if(!cpu.gdt.GetDescriptor(selector, desc))
+			{
+				rm = dbus_message_new_error(msg, DBUS_ERROR_INVALID_ARGS, "Selector isn't in the GDT or LDT.");
+			}
+			else
+			{
+				// the 8 bytes as they are in the table
+				uint64_t raw = ((uint64_t)desc.saved.fill[1] << 32) | desc.saved.fill[0];
+
+				rm = dbus_message_new_method_return(msg);
+				DBusMessageIter ri;
+				dbus_message_iter_init_append(rm, &ri);
+
+				dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT64, &raw);
+			}
+
+			if(!dbus_connection_send(conn, rm, NULL))
+				E_Exit("dbus error: dbus_connection_send failed");
+
+			dbus_connection_flush(conn);
+			dbus_message_unref(rm);
+
+			ret = DBUS_HANDLER_RESULT_HANDLED;
+		}
+		else
+			dbus_error_free(&err);
+	}
+
+	return ret;
+}
+
+#if C_FPU
+// R0..R7 as FSTP TBYTE would store them, the C core keeps doubles only
+static void GetFpuReg80(Bitu reg, uint8_t* out)
+{
+#if C_FPU_X86
+	memcpy(out, &fpu.p_regs[reg], 10);
+#else
+	Bit64u raw = (Bit64u)fpu.regs[reg].ll;
+	Bit64u mant = (raw & LONGTYPE(0x000fffffffffffff)) << 11;
+	Bit16u exp = (Bit16u)((raw >> 52) & 0x7ff);
+
+	if(exp == 0x7ff)
+	{
+		mant |= LONGTYPE(0x8000000000000000);
+		exp = 0x7fff; // infinities and NaNs stay so
+	}
+	else if(exp != 0)
+	{
+		mant |= LONGTYPE(0x8000000000000000);
+		exp += 16383 - 1023;
+	}
+	else if(mant != 0)
+	{
+		// a denormal double has no integer bit, the wider exponent normalises it
+		exp = 16383 - 1022;
+
+		while(!(mant >> 63))
+		{
+			mant <<= 1;
+			--exp;
+		}
+	}
+
+	if(raw >> 63) exp |= 0x8000;
+
+	for(int i = 0; i < 8; ++i)
+		out[i] = (uint8_t)(mant >> (i * 8));
+
+	out[8] = (uint8_t)exp;
+	out[9] = (uint8_t)(exp >> 8);
+#endif
+}
+
+DBusHandlerResult fpu_handler(DBusConnection* conn, DBusMessage* msg, void* data)
+{
+	DBusHandlerResult ret = DBUS_HANDLER_RESULT_NOT_YET_HANDLED;
+
+	if(dbus_message_is_method_call(msg, "com.dosbox", "get"))
+	{
+		DBusMessage* rm = dbus_message_new_method_return(msg);
+		DBusMessageIter ri;
+		dbus_message_iter_init_append(rm, &ri);
+
+		// TOP is kept apart from the status word until FNSTSW
+		uint16_t cw = (uint16_t)fpu.cw;
+		uint16_t sw = (uint16_t)((fpu.sw & ~0x3800) | ((fpu.top & 7) << 11));
+		uint16_t tw = 0;
+
+		for(Bitu i = 0; i < 8; ++i)
+			tw |= (fpu.tags[i] & 3) << (i * 2);
+
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT16, &cw);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT16, &sw);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT16, &tw);
+
+		DBusMessageIter bytes;
+		dbus_message_iter_open_container(&ri, DBUS_TYPE_ARRAY, DBUS_TYPE_BYTE_AS_STRING, &bytes);
+
+		for(Bitu i = 0; i < 8; ++i)
+		{
+			uint8_t reg[10];
+			GetFpuReg80(i, reg);
+
+			for(int j = 0; j < 10; ++j)
+				dbus_message_iter_append_basic(&bytes, DBUS_TYPE_BYTE, &reg[j]);
+		}
+
+		dbus_message_iter_close_container(&ri, &bytes);
+
+		if(!dbus_connection_send(conn, rm, NULL))
+			E_Exit("dbus error: dbus_connection_send failed");
+
+		dbus_connection_flush(conn);
+		dbus_message_unref(rm);
+
+		ret = DBUS_HANDLER_RESULT_HANDLED;
+	}
 
 	return ret;
 }
+#endif
+
+DBusHandlerResult bp_handler(DBusConnection* conn, DBusMessage* msg, void* data)
+{
+	DBusHandlerResult ret = DBUS_HANDLER_RESULT_NOT_YET_HANDLED;
+
+	DBusError err;
+	dbus_error_init(&err);
+
+	uint16_t segment;
+	uint32_t offset;
+	dbus_bool_t done = false;
+
+	if(dbus_message_is_method_call(msg, "com.dosbox", "add"))
+	{
+		dbus_bool_t once;
+
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_BOOLEAN, &once,
+			DBUS_TYPE_INVALID))
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		if(!CBreakpoint::IsBreakpoint(GetAddress(segment, offset)))
+		{
+			CBreakpoint::AddBreakpoint(segment, offset, once);
+			done = true;
+		}
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "remove"))
+	{
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_INVALID))
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		done = CBreakpoint::DeleteBreakpoint(GetAddress(segment, offset));
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "add_int"))
+	{
+		uint8_t intNum;
+		uint16_t ah;
+		dbus_bool_t once;
+
+		// AH of BPINT_ALL (0x100) matches any function
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_BYTE, &intNum,
+			DBUS_TYPE_UINT16, &ah,
+			DBUS_TYPE_BOOLEAN, &once,
+			DBUS_TYPE_INVALID))
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		CBreakpoint::DeleteIntBreakpoint(intNum, ah);
+		CBreakpoint::AddIntBreakpoint(intNum, ah, once);
+		done = true;
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "remove_int"))
+	{
+		uint8_t intNum;
+		uint16_t ah;
+
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_BYTE, &intNum,
+			DBUS_TYPE_UINT16, &ah,
+			DBUS_TYPE_INVALID))
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		done = CBreakpoint::DeleteIntBreakpoint(intNum, ah);
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "add_mem"))
+	{
+		uint8_t type;
+
+		// type is 0 for seg:off, 1 for selector:offset in pmode and 2 for a linear offset
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_BYTE, &type,
+			DBUS_TYPE_INVALID) || type > 2)
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		EBreakpoint bpType = (EBreakpoint)(BKPNT_MEMORY + type);
+
+		// replacing an existing one also resets the value it compares to
+		CBreakpoint::DeleteMemBreakpoint(segment, offset, bpType);
+		CBreakpoint::AddMemBreakpoint(segment, offset, bpType);
+		done = true;
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "remove_mem"))
+	{
+		uint8_t type;
+
+		if(!dbus_message_get_args(msg, &err,
+			DBUS_TYPE_UINT16, &segment,
+			DBUS_TYPE_UINT32, &offset,
+			DBUS_TYPE_BYTE, &type,
+			DBUS_TYPE_INVALID) || type > 2)
+		{
+			dbus_error_free(&err);
+			return ret;
+		}
+
+		done = CBreakpoint::DeleteMemBreakpoint(segment, offset, (EBreakpoint)(BKPNT_MEMORY + type));
+	}
+	else if(dbus_message_is_method_call(msg, "com.dosbox", "watch_hit"))
+	{
+		DBusMessage* rm = dbus_message_new_method_return(msg);
+		DBusMessageIter ri;
+		dbus_message_iter_init_append(rm, &ri);
+
+		uint8_t type = _watchHit.valid ? (uint8_t)(_watchHit.type - BKPNT_MEMORY) : 0;
+
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &_watchHit.valid);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BYTE, &type);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT16, &_watchHit.segment);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &_watchHit.offset);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BYTE, &_watchHit.oldValue);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_BYTE, &_watchHit.newValue);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT16, &_watchHit.cs);
+		dbus_message_iter_append_basic(&ri, DBUS_TYPE_UINT32, &_watchHit.eip);
+
+		if(!dbus_connection_send(conn, rm, NULL))
+			E_Exit("dbus error: dbus_connection_send failed");
+
+		dbus_connection_flush(conn);
+		dbus_message_unref(rm);
+
+		return DBUS_HANDLER_RESULT_HANDLED;
+	}
+	else
+		return ret;
+
+	DBusMessage* rm = dbus_message_new_method_return(msg);
+	DBusMessageIter ri;
+	dbus_message_iter_init_append(rm, &ri);
+
+	dbus_message_iter_append_basic(&ri, DBUS_TYPE_BOOLEAN, &done);
+
+	if(!dbus_connection_send(conn, rm, NULL))
+		E_Exit("dbus error: dbus_connection_send failed");
+
+	dbus_connection_flush(conn);
+	dbus_message_unref(rm);
+
+	return DBUS_HANDLER_RESULT_HANDLED;
+}
 
 Bitu debugCallback;
 
@@ -1380,12 +2170,22 @@ void DEBUG_Init(Section* sec)
 	DBusObjectPathVTable dbg_h = { &unregister_handler, &dbg_handler };
 	DBusObjectPathVTable cpu_h = { &unregister_handler, &cpu_handler };
 	DBusObjectPathVTable reg_h = { &unregister_handler, &cpu_regs_handler };
+	DBusObjectPathVTable desc_h = { &unregister_handler, &desc_handler };
 	DBusObjectPathVTable mem_h = { &unregister_handler, &mem_handler };
+	DBusObjectPathVTable bp_h = { &unregister_handler, &bp_handler };
+#if C_FPU
+	DBusObjectPathVTable fpu_h = { &unregister_handler, &fpu_handler };
+#endif
 
 	dbus_connection_register_object_path(_conn, "/dbg", &dbg_h, NULL);
 	dbus_connection_register_object_path(_conn, "/cpu", &cpu_h, NULL);
 	dbus_connection_register_fallback(_conn, "/cpu/regs", &reg_h, NULL);
+	dbus_connection_register_object_path(_conn, "/cpu/desc", &desc_h, NULL);
 	dbus_connection_register_fallback(_conn, "/mem", &mem_h, NULL);
+	dbus_connection_register_object_path(_conn, "/bp", &bp_h, NULL);
+#if C_FPU
+	dbus_connection_register_object_path(_conn, "/fpu", &fpu_h, NULL);
+#endif
 
 //	dbus_connection_add_filter(_conn, dbus_filter, NULL, NULL);
 
@@ -1398,6 +2198,8 @@ void DEBUG_Init(Section* sec)
 	/* setup debug.com */
 	PROGRAMS_MakeFile("DEBUG.COM",DEBUG_ProgramStart);
 
+	PIC_AddEvent(DEBUG_PollDBus, 10.0f);
+
 	/* Setup callback */
 	debugCallback=CALLBACK_Allocate();
 	CALLBACK_Setup(debugCallback,DEBUG_EnableDebugger,CB_RETF,"debugger");
@@ -1432,6 +2234,18 @@ void DEBUG_HeavyWriteLogInstruction()
 
 bool DEBUG_HeavyIsBreakpoint()
 {
+	// Breakpoints aren't patched in as 0xCC here, every instruction is checked instead
+	if(CBreakpoint::CheckBreakpoint(SegValue(cs), reg_eip))
+	{
+		CBreakpoint::ActivateBreakpoints(GetAddress(SegValue(cs), reg_eip), false);
+		notify_break();
+
+		return true;
+	}
+
+	_lastCs = SegValue(cs);
+	_lastEip = reg_eip;
+
 	return false;
 }
 
@@ -0,0 +1,334 @@
+use anyhow::{bail, Result};
+use std::fmt;
+
+use crate::{
+    bus::Regs,
+    target::Target,
+    x86::{
+        expr::{parse_number, Expr, Location},
+        Address,
+    },
+};
+
+/// What makes the target stop.
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Code(Address),
+    /// `INT num`, only when AH holds the given function if there is one.
+    Int {
+        num: u8,
+        ah: Option<u8>,
+    },
+    /// Write to the byte at the address, a physical one can't be watched.
+    Write(Address),
+}
+
+/// Change of a watched byte that stopped the target.
+#[derive(Clone, Copy, PartialEq)]
+pub struct WatchHit {
+    pub kind: Kind,
+    pub old: u8,
+    pub new: u8,
+    /// The instruction executed right before the stop.
+    pub writer: Address,
+}
+
+impl Kind {
+    /// Parses an interrupt breakpoint like `21`, `int 21h ah=3D`, `21 ah = 3D` or `21 3D`.
+    pub fn parse_int(text: &str) -> Result<Self> {
+        let lower = text.trim().to_ascii_lowercase();
+        let lower = lower.strip_prefix("int ").unwrap_or(&lower).trim_start();
+        let (num, rest) = lower
+            .split_once(|c: char| c.is_whitespace() || c == ',')
+            .unwrap_or((lower, ""));
+
+        if num.is_empty() {
+            bail!("Expected an interrupt number.");
+        }
+
+        let num = parse_byte(num)?;
+        let rest = rest.trim_start_matches(|c: char| c.is_whitespace() || c == ',');
+
+        // the AH value may come with its name, spaces around `=` or not
+        let (named, value) = match rest.strip_prefix("ah") {
+            Some(r) => (true, r.trim_start().trim_start_matches('=')),
+            None => (false, rest),
+        };
+
+        let mut words = value.split_whitespace();
+
+        let ah = match words.next() {
+            Some(ah) => Some(parse_byte(ah)?),
+            None if named => bail!("Expected a value of AH."),
+            None => None,
+        };
+
+        if let Some(w) = words.next() {
+            bail!("Unexpected `{w}`.");
+        }
+
+        Ok(Self::Int { num, ah })
+    }
+
+    /// Parses a watchpoint at a location like `1000:0200`, `pm 0008:00001234` or `#12345`.
+    ///
+    /// A plain offset is taken in DS.
+    pub fn parse_watch(text: &str, regs: &Regs, target: &dyn Target) -> Result<Self> {
+        let lower = text.trim().to_ascii_lowercase();
+        let lower = lower.strip_prefix("wr ").unwrap_or(&lower);
+
+        Ok(Self::Write(
+            Location::parse(lower)?.eval(regs, target, regs.ds)?,
+        ))
+    }
+
+    /// Tells whether the breakpoint is what stopped the target at CS:EIP.
+    fn matches(&self, target: &dyn Target, regs: &Regs, watch: Option<&WatchHit>) -> Result<bool> {
+        let ip = target.cpu_mode()?.address(regs.cs, regs.eip);
+
+        Ok(match *self {
+            Self::Code(addr) => addr == ip,
+            Self::Int { num, ah } => {
+                // the CPU stops on the `int` instruction itself, before the call
+                target.read_mem(ip, 2)? == [0xCD, num]
+                    && ah.is_none_or(|ah| u32::from(ah) == (regs.eax >> 8) & 0xFF)
+            }
+            Self::Write(_) => watch.is_some_and(|w| w.kind == *self),
+        })
+    }
+}
+
+impl fmt::Display for Kind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Code(addr) => write!(f, "{addr}"),
+            Self::Int { num, ah: None } => write!(f, "INT {num:02X}"),
+            Self::Int { num, ah: Some(ah) } => write!(f, "INT {num:02X} AH={ah:02X}"),
+            Self::Write(addr) => write!(f, "WR {addr}"),
+        }
+    }
+}
+
+fn parse_byte(word: &str) -> Result<u8> {
+    match u8::try_from(parse_number(word)?) {
+        Ok(b) => Ok(b),
+        Err(_) => bail!("`{word}` doesn't fit in a byte."),
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Breakpoint {
+    pub kind: Kind,
+    pub enabled: bool,
+    pub once: bool,
+    pub condition: Option<Condition>,
+    /// Number of hits the breakpoint lets through before it stops.
+    pub count: Option<u32>,
+    pub hits: u32,
+}
+
+#[derive(Clone, PartialEq)]
+pub struct Condition {
+    pub text: String,
+    expr: Expr,
+}
+
+impl Condition {
+    pub fn parse(text: &str) -> Result<Self> {
+        Ok(Self {
+            text: text.trim().to_owned(),
+            expr: Expr::parse(text)?,
+        })
+    }
+
+    pub fn holds(&self, regs: &Regs, target: &dyn Target) -> Result<bool> {
+        Ok(self.expr.eval(regs, target)? != 0)
+    }
+}
+
+/// Breakpoints set by the user, only the enabled ones are armed in the target.
+#[derive(Clone, Default, PartialEq)]
+pub struct Breakpoints(Vec<Breakpoint>);
+
+impl Breakpoints {
+    pub fn get(&self, addr: Address) -> Option<&Breakpoint> {
+        self.0.iter().find(|b| b.kind == Kind::Code(addr))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Breakpoint> {
+        self.0.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn toggle(&mut self, target: &dyn Target, addr: Address) -> Result<()> {
+        match self.0.iter().position(|b| b.kind == Kind::Code(addr)) {
+            Some(i) => self.remove(target, i),
+            None => self.add(target, Kind::Code(addr), false),
+        }
+    }
+
+    pub fn add(&mut self, target: &dyn Target, kind: Kind, once: bool) -> Result<()> {
+        if self.0.iter().any(|b| b.kind == kind) {
+            bail!("Breakpoint {kind} is already set.");
+        }
+
+        target.add_breakpoint(kind, once)?;
+
+        self.0.push(Breakpoint {
+            kind,
+            enabled: true,
+            once,
+            condition: None,
+            count: None,
+            hits: 0,
+        });
+
+        Ok(())
+    }
+
+    pub fn remove(&mut self, target: &dyn Target, index: usize) -> Result<()> {
+        if self.0.get(index).is_some_and(|b| b.enabled) {
+            target.remove_breakpoint(self.0[index].kind)?;
+        }
+
+        if index < self.0.len() {
+            self.0.remove(index);
+        }
+
+        Ok(())
+    }
+
+    pub fn enable(&mut self, target: &dyn Target, index: usize, enabled: bool) -> Result<()> {
+        let Some(bp) = self.0.get_mut(index) else {
+            return Ok(());
+        };
+
+        if bp.enabled != enabled {
+            if enabled {
+                target.add_breakpoint(bp.kind, bp.once)?;
+            } else {
+                target.remove_breakpoint(bp.kind)?;
+            }
+
+            bp.enabled = enabled;
+        }
+
+        Ok(())
+    }
+
+    pub fn get_index(&self, index: usize) -> Option<&Breakpoint> {
+        self.0.get(index)
+    }
+
+    pub fn set_condition(&mut self, index: usize, condition: Option<Condition>) {
+        if let Some(bp) = self.0.get_mut(index) {
+            bp.condition = condition;
+        }
+    }
+
+    pub fn set_count(&mut self, index: usize, count: Option<u32>) {
+        if let Some(bp) = self.0.get_mut(index) {
+            bp.count = count;
+            bp.hits = 0;
+        }
+    }
+
+    /// Counts a hit of the breakpoint the target stopped at and tells whether it should stay stopped.
+    ///
+    /// Stops that aren't caused by a breakpoint are always kept.
+    pub fn hit(
+        &mut self,
+        target: &dyn Target,
+        regs: &Regs,
+        watch: Option<&WatchHit>,
+    ) -> Result<bool> {
+        let mut found = None;
+
+        for (i, bp) in self.0.iter().enumerate() {
+            if bp.enabled && bp.kind.matches(target, regs, watch)? {
+                found = Some(i);
+                break;
+            }
+        }
+
+        let Some(i) = found else {
+            return Ok(true);
+        };
+
+        let bp = &mut self.0[i];
+        let holds = match &bp.condition {
+            Some(c) => c.holds(regs, target)?,
+            None => true,
+        };
+
+        if holds {
+            bp.hits += 1;
+        }
+
+        let stop = holds && bp.count.is_none_or(|n| bp.hits >= n);
+
+        // the target has already dropped a one-shot breakpoint
+        if bp.once {
+            if stop {
+                self.0.remove(i);
+            } else {
+                target.add_breakpoint(bp.kind, true)?;
+            }
+        }
+
+        Ok(stop)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn int(text: &str) -> Kind {
+        Kind::parse_int(text).unwrap()
+    }
+
+    #[test]
+    fn int_breakpoints() {
+        let any = Kind::Int {
+            num: 0x21,
+            ah: None,
+        };
+        let open = Kind::Int {
+            num: 0x21,
+            ah: Some(0x3D),
+        };
+
+        assert!(int("21") == any);
+        assert!(int(" int 21h ") == any);
+        assert!(int("21 ah=3D") == open);
+        assert!(int("21 ah = 3D") == open);
+        assert!(int("21 AH= 3d") == open);
+        assert!(int("int 21h ah=3Dh") == open);
+        assert!(int("21 3D") == open);
+        assert!(int("21,3D") == open);
+    }
+
+    #[test]
+    fn bad_int_breakpoints() {
+        for text in [
+            "",
+            "int",
+            "100",
+            "21 ah",
+            "21 ah=",
+            "21 ah=100",
+            "21 3D 4",
+            "21 ah 3D x",
+        ] {
+            assert!(Kind::parse_int(text).is_err(), "{text}");
+        }
+    }
+}
@@ -1,22 +1,174 @@
-use serde::Deserialize;
-use zbus::{blocking::Connection, dbus_proxy, zvariant::Type, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::Mutex;
+use zbus::{blocking::Connection, dbus_interface, dbus_proxy, zvariant::Type, Result};
+
+use crate::{
+    breakpoints::{Kind, WatchHit},
+    target::Target,
+    x86::{desc::Table, Address},
+};
 
 pub struct Proxy {
     pub cpu: CpuProxyBlocking<'static>,
+    pub desc: DescriptorsProxyBlocking<'static>,
+    pub fpu: FpuProxyBlocking<'static>,
     pub regs: RegsProxyBlocking<'static>,
     pub mem: MemoryProxyBlocking<'static>,
+    pub bp: BreakpointsProxyBlocking<'static>,
+    conn: Connection,
 }
 
 impl Proxy {
     pub fn new(conn: &Connection) -> Result<Self> {
         Ok(Self {
             cpu: CpuProxyBlocking::new(conn)?,
+            desc: DescriptorsProxyBlocking::new(conn)?,
+            fpu: FpuProxyBlocking::new(conn)?,
             regs: RegsProxyBlocking::new(conn)?,
             mem: MemoryProxyBlocking::new(conn)?,
+            bp: BreakpointsProxyBlocking::new(conn)?,
+            conn: conn.clone(),
         })
     }
 }
 
+impl Target for Proxy {
+    fn cpu_mode(&self) -> anyhow::Result<CpuMode> {
+        Ok(self.cpu.get()?)
+    }
+
+    fn control_regs(&self) -> anyhow::Result<ControlRegs> {
+        Ok(self.cpu.control()?)
+    }
+
+    fn descriptor_table(&self, table: Table) -> anyhow::Result<(u32, Vec<u8>)> {
+        Ok(self.desc.get(table as u8)?)
+    }
+
+    fn descriptor(&self, selector: u16) -> anyhow::Result<u64> {
+        Ok(self.desc.lookup(selector)?)
+    }
+
+    fn step_in(&self) -> anyhow::Result<u32> {
+        Ok(self.cpu.step_in()?)
+    }
+
+    fn run(&self) -> anyhow::Result<u32> {
+        Ok(self.cpu.run()?)
+    }
+
+    fn break_in(&self) -> anyhow::Result<u32> {
+        Ok(self.cpu.break_in()?)
+    }
+
+    fn on_break(&self, callback: Box<dyn Fn() + Send>) -> anyhow::Result<()> {
+        self.conn
+            .object_server()
+            .at("/", Dbg(Mutex::new(callback)))?;
+        self.conn.request_name("com.dosbox.dbg")?;
+
+        Ok(())
+    }
+
+    fn add_breakpoint(&self, kind: Kind, once: bool) -> anyhow::Result<()> {
+        let added = match kind {
+            Kind::Code(addr) => {
+                let (segment, offset) = code_args(addr)?;
+                self.bp.add(segment, offset, once)?
+            }
+            Kind::Int { num, ah } => self.bp.add_int(num, int_ah(ah), once)?,
+            Kind::Write(addr) => {
+                let (segment, offset, watch) = watch_args(addr)?;
+                self.bp.add_mem(segment, offset, watch)?
+            }
+        };
+
+        if !added {
+            anyhow::bail!("Breakpoint {kind} is already set.");
+        }
+
+        Ok(())
+    }
+
+    fn remove_breakpoint(&self, kind: Kind) -> anyhow::Result<()> {
+        match kind {
+            Kind::Code(addr) => {
+                let (segment, offset) = code_args(addr)?;
+                self.bp.remove(segment, offset)?
+            }
+            Kind::Int { num, ah } => self.bp.remove_int(num, int_ah(ah))?,
+            Kind::Write(addr) => {
+                let (segment, offset, watch) = watch_args(addr)?;
+                self.bp.remove_mem(segment, offset, watch)?
+            }
+        };
+
+        Ok(())
+    }
+
+    fn watch_hit(&self) -> anyhow::Result<Option<WatchHit>> {
+        let (valid, watch, segment, offset, old, new, cs, eip) = self.bp.watch_hit()?;
+
+        if !valid {
+            return Ok(None);
+        }
+
+        let addr = match watch {
+            0 => Address::Real(segment, offset),
+            1 => Address::Prot(segment, offset),
+            _ => Address::Linear(offset),
+        };
+
+        Ok(Some(WatchHit {
+            kind: Kind::Write(addr),
+            old,
+            new,
+            writer: self.cpu_mode()?.address(cs, eip),
+        }))
+    }
+
+    fn regs(&self) -> anyhow::Result<Regs> {
+        Ok(self.regs.get()?)
+    }
+
+    fn fpu(&self) -> anyhow::Result<FpuRegs> {
+        Ok(self.fpu.get()?)
+    }
+
+    fn set_reg(&self, name: &str, value: u32) -> anyhow::Result<()> {
+        if !self.regs.set(name, value)? {
+            anyhow::bail!("Unknown register `{name}`.");
+        }
+
+        Ok(())
+    }
+
+    fn read_mem(&self, addr: Address, length: u32) -> anyhow::Result<Vec<u8>> {
+        let (mode, segment, offset) = mem_args(addr);
+
+        Ok(self.mem.read(mode, segment, offset, length)?)
+    }
+
+    fn write_mem(&self, addr: Address, value: u8) -> anyhow::Result<u8> {
+        let (mode, segment, offset) = mem_args(addr);
+
+        Ok(self.mem.write(mode, segment, offset, value)?)
+    }
+}
+
+/// `com.dosbox.dbg` service DOSBox calls from `DEBUG_Breakpoint` when the emulated CPU stops.
+struct Dbg(Mutex<Box<dyn Fn() + Send>>);
+
+#[dbus_interface(name = "com.dosbox.dbg")]
+impl Dbg {
+    #[dbus_interface(name = "breakNow")]
+    fn break_now(&self) -> bool {
+        (self.0.lock().unwrap())();
+
+        true
+    }
+}
+
 #[dbus_proxy(
     interface = "com.dosbox",
     default_service = "com.dosbox",
@@ -24,7 +176,10 @@ impl Proxy {
 )]
 trait Cpu {
     #[dbus_proxy(name = "get")]
-    fn get(&self) -> Result<(bool, bool)>;
+    fn get(&self) -> Result<CpuMode>;
+
+    #[dbus_proxy(name = "control")]
+    fn control(&self) -> Result<ControlRegs>;
 
     #[dbus_proxy(name = "callback_info")]
     fn callback_info(&self, index: u16) -> Result<String>;
@@ -34,6 +189,36 @@ trait Cpu {
 
     #[dbus_proxy(name = "run")]
     fn run(&self) -> Result<u32>;
+
+    #[dbus_proxy(name = "break")]
+    fn break_in(&self) -> Result<u32>;
+}
+
+#[dbus_proxy(
+    interface = "com.dosbox",
+    default_service = "com.dosbox",
+    default_path = "/cpu/desc"
+)]
+trait Descriptors {
+    /// `table` is 0 for the GDT, 1 for the LDT and 2 for the IDT, the reply is its linear base
+    /// and contents. Only the IDT is there in real mode.
+    #[dbus_proxy(name = "get")]
+    fn get(&self, table: u8) -> Result<(u32, Vec<u8>)>;
+
+    /// One descriptor of the GDT or the LDT, without fetching the whole table.
+    #[dbus_proxy(name = "lookup")]
+    fn lookup(&self, selector: u16) -> Result<u64>;
+}
+
+#[dbus_proxy(
+    interface = "com.dosbox",
+    default_service = "com.dosbox",
+    default_path = "/fpu"
+)]
+trait Fpu {
+    /// Only there when DOSBox is built with the FPU.
+    #[dbus_proxy(name = "get")]
+    fn get(&self) -> Result<FpuRegs>;
 }
 
 #[dbus_proxy(
@@ -44,6 +229,10 @@ trait Cpu {
 trait Regs {
     #[dbus_proxy(name = "get")]
     fn get(&self) -> Result<Regs>;
+
+    /// Names are the lower-case 32-bit registers, segments, flags, `iopl` and `efl`.
+    #[dbus_proxy(name = "set")]
+    fn set(&self, name: &str, value: u32) -> Result<bool>;
 }
 
 #[dbus_proxy(
@@ -52,14 +241,125 @@ trait Regs {
     default_path = "/mem"
 )]
 trait Memory {
-    #[dbus_proxy(name = "get")]
-    fn get(&self, segment: u16, offset: u32, length: u32) -> Result<Vec<u8>>;
+    /// `mode` is 0 for `segment:offset` of real mode, 1 for `selector:offset`, 2 for a linear
+    /// and 3 for a physical `offset`, see `mem_args`.
+    #[dbus_proxy(name = "read")]
+    fn read(&self, mode: u8, segment: u16, offset: u32, length: u32) -> Result<Vec<u8>>;
 
-    #[dbus_proxy(name = "set")]
-    fn set(&self, segment: u16, offset: u32, value: u8) -> Result<u8>;
+    #[dbus_proxy(name = "write")]
+    fn write(&self, mode: u8, segment: u16, offset: u32, value: u8) -> Result<u8>;
+}
+
+#[dbus_proxy(
+    interface = "com.dosbox",
+    default_service = "com.dosbox",
+    default_path = "/bp"
+)]
+trait Breakpoints {
+    #[dbus_proxy(name = "add")]
+    fn add(&self, segment: u16, offset: u32, once: bool) -> Result<bool>;
+
+    #[dbus_proxy(name = "remove")]
+    fn remove(&self, segment: u16, offset: u32) -> Result<bool>;
+
+    /// `ah` of 0x100 (`BPINT_ALL`) matches any function.
+    #[dbus_proxy(name = "add_int")]
+    fn add_int(&self, num: u8, ah: u16, once: bool) -> Result<bool>;
+
+    #[dbus_proxy(name = "remove_int")]
+    fn remove_int(&self, num: u8, ah: u16) -> Result<bool>;
+
+    /// `watch` is 0 for seg:off, 1 for selector:offset and 2 for a linear `offset`.
+    #[dbus_proxy(name = "add_mem")]
+    fn add_mem(&self, segment: u16, offset: u32, watch: u8) -> Result<bool>;
+
+    #[dbus_proxy(name = "remove_mem")]
+    fn remove_mem(&self, segment: u16, offset: u32, watch: u8) -> Result<bool>;
+
+    #[dbus_proxy(name = "watch_hit")]
+    fn watch_hit(&self) -> Result<RawWatchHit>;
 }
 
-#[derive(Copy, Clone, Default, Deserialize, PartialEq, Type)]
+/// `(valid, watch, segment, offset, old, new, cs, eip)` of the last watchpoint stop.
+pub type RawWatchHit = (bool, u8, u16, u32, u8, u8, u16, u32);
+
+/// `(mode, segment, offset)` of the memory methods.
+pub fn mem_args(addr: Address) -> (u8, u16, u32) {
+    match addr {
+        Address::Real(segment, offset) => (0, segment, offset),
+        Address::Prot(selector, offset) => (1, selector, offset),
+        Address::Linear(addr) => (2, 0, addr),
+        Address::Physical(addr) => (3, 0, addr),
+    }
+}
+
+/// The other way around of `mem_args`.
+pub fn mem_address(mode: u8, segment: u16, offset: u32) -> Option<Address> {
+    Some(match mode {
+        0 => Address::Real(segment, offset),
+        1 => Address::Prot(segment, offset),
+        2 => Address::Linear(offset),
+        3 => Address::Physical(offset),
+        _ => return None,
+    })
+}
+
+/// `CBreakpoint` keeps code breakpoints as `segment:offset` in whatever mode the CPU is.
+fn code_args(addr: Address) -> anyhow::Result<(u16, u32)> {
+    match addr {
+        Address::Real(segment, offset) | Address::Prot(segment, offset) => Ok((segment, offset)),
+        _ => anyhow::bail!("Code breakpoint at {addr} needs a segment."),
+    }
+}
+
+/// `(segment, offset, watch)` of a memory breakpoint, see `add_mem`.
+pub fn watch_args(addr: Address) -> anyhow::Result<(u16, u32, u8)> {
+    match addr {
+        Address::Real(segment, offset) => Ok((segment, offset, 0)),
+        Address::Prot(selector, offset) => Ok((selector, offset, 1)),
+        Address::Linear(addr) => Ok((0, addr, 2)),
+        Address::Physical(_) => anyhow::bail!("Physical address {addr} can't be watched."),
+    }
+}
+
+/// AH filter as `CBreakpoint` stores it, `BPINT_ALL` stands for any.
+fn int_ah(ah: Option<u8>) -> u16 {
+    ah.map_or(0x100, u16::from)
+}
+
+#[derive(Copy, Clone, Default, Deserialize, PartialEq, Serialize, Type)]
+pub struct CpuMode {
+    pub pmode: bool,
+    /// The code segment is 32-bit.
+    pub big: bool,
+    /// The stack segment is 32-bit, so is the stack pointer.
+    pub stack_big: bool,
+    /// Virtual 8086 mode, segments are real mode ones although `pmode` is set.
+    pub v86: bool,
+}
+
+impl CpuMode {
+    /// Address of `segment:offset` the way the CPU sees it now.
+    pub fn address(&self, segment: u16, offset: u32) -> Address {
+        if self.pmode && !self.v86 {
+            Address::Prot(segment, offset)
+        } else {
+            Address::Real(segment, offset)
+        }
+    }
+}
+
+#[derive(Copy, Clone, Default, Deserialize, PartialEq, Serialize, Type)]
+pub struct ControlRegs {
+    pub cr0: u32,
+    /// Linear address of the last page fault.
+    pub cr2: u32,
+    /// Physical address of the page directory.
+    pub cr3: u32,
+    pub cr4: u32,
+}
+
+#[derive(Copy, Clone, Default, Deserialize, PartialEq, Serialize, Type)]
 pub struct Regs {
     pub eax: u32,
     pub ebx: u32,
@@ -91,3 +391,39 @@ pub struct Regs {
     pub _ac: bool,
     pub _id: bool,
 }
+
+impl Regs {
+    /// EFLAGS put together from the flags, bit 1 is always set.
+    pub fn eflags(&self) -> u32 {
+        [
+            (self.cf, 0),
+            (self.pf, 2),
+            (self.af, 4),
+            (self.zf, 6),
+            (self.sf, 7),
+            (self.tf, 8),
+            (self.r#if, 9),
+            (self.df, 10),
+            (self.of, 11),
+            (self._nt, 14),
+            (self._vm, 17),
+            (self._ac, 18),
+            (self._id, 21),
+        ]
+        .into_iter()
+        .filter(|(set, _)| *set)
+        .fold(2 | (u32::from(self._iopl & 3) << 12), |a, (_, bit)| {
+            a | (1 << bit)
+        })
+    }
+}
+
+/// x87 state, `regs` holds R0..R7 as 80-bit values and ST(i) is R((TOP + i) mod 8).
+#[derive(Clone, Default, Deserialize, PartialEq, Serialize, Type)]
+pub struct FpuRegs {
+    pub cw: u16,
+    pub sw: u16,
+    /// Two bits per physical register: valid, zero, special and empty.
+    pub tw: u16,
+    pub regs: Vec<u8>,
+}
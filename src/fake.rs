@@ -0,0 +1,612 @@
+use anyhow::{bail, Result};
+use iced_x86::{Decoder, DecoderOptions, Mnemonic};
+use std::{
+    fs,
+    sync::{Arc, Mutex},
+};
+use zbus::{
+    blocking::{Connection, ConnectionBuilder},
+    dbus_interface, fdo, MessageBuilder, MessageFlags,
+};
+
+use crate::{
+    breakpoints::{Kind, WatchHit},
+    bus::{mem_address, watch_args, ControlRegs, CpuMode, FpuRegs, RawWatchHit, Regs},
+    target::Target,
+    x86::{desc::Table, expr::set_register, Address},
+};
+
+const MEMORY_SIZE: usize = 1 << 20; // 1 MiB of real mode memory
+const LOAD_SEGMENT: u16 = 0x1000; // where an image is loaded, like a .COM file
+const LOAD_OFFSET: u32 = 0x100;
+const IVT_SIZE: usize = 0x400;
+const RUN_LIMIT: usize = 0x10000; // number of instructions `run` walks before it breaks by itself
+const READ_LIMIT: u32 = 0x10000; // number of bytes `read` returns at most, like DOSBox
+
+/// Flat memory image and register file standing in for an emulated CPU.
+///
+/// Instructions are decoded but hardly executed: stepping moves EIP past the
+/// current instruction (only `jmp` and `loop` branch) and `run` walks on until
+/// it meets an `int3` or a breakpoint. Watched bytes change only when written
+/// over D-Bus.
+pub struct Machine {
+    state: Mutex<State>,
+    on_break: Mutex<Option<Box<dyn Fn() + Send>>>,
+}
+
+struct State {
+    regs: Regs,
+    mem: Vec<u8>,
+    breakpoints: Vec<(Kind, bool)>,
+    watches: Vec<(Kind, u8)>,
+    watch_hit: Option<WatchHit>,
+    gdt: Vec<u8>,
+}
+
+impl Machine {
+    pub fn new(image: &[u8]) -> Self {
+        let mut mem = vec![0u8; MEMORY_SIZE];
+
+        let base = real(LOAD_SEGMENT, 0);
+        mem[base..base + 2].copy_from_slice(&[0xCD, 0x20]); // int 20h at PSP:0000 like DOS does
+
+        let start = base + LOAD_OFFSET as usize;
+        let len = image.len().min(MEMORY_SIZE - start);
+        mem[start..start + len].copy_from_slice(&image[..len]);
+
+        let regs = Regs {
+            eip: LOAD_OFFSET,
+            esp: 0xFFFE,
+            cs: LOAD_SEGMENT,
+            ds: LOAD_SEGMENT,
+            es: LOAD_SEGMENT,
+            ss: LOAD_SEGMENT,
+            r#if: true,
+            ..Default::default()
+        };
+
+        Self {
+            state: Mutex::new(State {
+                regs,
+                mem,
+                breakpoints: Vec::new(),
+                watches: Vec::new(),
+                watch_hit: None,
+                gdt: Vec::new(),
+            }),
+            on_break: Mutex::new(None),
+        }
+    }
+}
+
+impl Machine {
+    /// Sets the GDT the way `lgdt` does, the CPU stays in real mode.
+    pub fn load_gdt(&self, table: &[u8]) {
+        self.state.lock().unwrap().gdt = table.to_vec();
+    }
+
+    fn notify_break(&self) {
+        if let Some(callback) = self.on_break.lock().unwrap().as_ref() {
+            callback();
+        }
+    }
+}
+
+impl State {
+    /// Moves EIP past the instruction, or to the target of a `jmp` or a `loop` that is taken.
+    fn execute(&mut self) {
+        let addr = real(self.regs.cs, self.regs.eip);
+        let end = MEMORY_SIZE.min(addr + 15);
+        let mut dec = Decoder::with_ip(
+            16,
+            &self.mem[addr..end],
+            self.regs.eip.into(),
+            DecoderOptions::NONE,
+        );
+        let ins = dec.decode();
+
+        let taken = if ins.mnemonic() == Mnemonic::Loop {
+            let cx = (self.regs.ecx as u16).wrapping_sub(1);
+            self.regs.ecx = (self.regs.ecx & 0xFFFF_0000) | u32::from(cx);
+            cx != 0
+        } else {
+            ins.is_jmp_short_or_near()
+        };
+
+        self.regs.eip = if taken {
+            ins.near_branch_target() as u32
+        } else {
+            ins.next_ip32()
+        } & 0xFFFF;
+    }
+
+    fn stops_at(&self, kind: Kind, ip: Address) -> bool {
+        match kind {
+            Kind::Code(addr) => addr == ip,
+            Kind::Int { num, ah } => {
+                let Ok(at) = linear(ip) else {
+                    return false;
+                };
+
+                self.mem[at] == 0xCD
+                    && self.mem[(at + 1) % MEMORY_SIZE] == num
+                    && ah.is_none_or(|ah| u32::from(ah) == (self.regs.eax >> 8) & 0xFF)
+            }
+            _ => false,
+        }
+    }
+
+    fn watched(&self, kind: Kind) -> u8 {
+        match kind {
+            Kind::Write(addr) | Kind::Code(addr) => linear(addr).map_or(0, |a| self.mem[a]),
+            Kind::Int { .. } => 0,
+        }
+    }
+
+    /// Finds a watched byte that has changed and remembers its new value.
+    fn watch_changed(&mut self, writer: Address) -> Option<WatchHit> {
+        let i = self
+            .watches
+            .iter()
+            .position(|(k, v)| self.watched(*k) != *v)?;
+
+        let (kind, old) = self.watches[i];
+        let new = self.watched(kind);
+        self.watches[i].1 = new;
+
+        Some(WatchHit {
+            kind,
+            old,
+            new,
+            writer,
+        })
+    }
+}
+
+impl Target for Machine {
+    fn cpu_mode(&self) -> Result<CpuMode> {
+        Ok(CpuMode::default())
+    }
+
+    fn control_regs(&self) -> Result<ControlRegs> {
+        Ok(ControlRegs::default())
+    }
+
+    fn descriptor_table(&self, table: Table) -> Result<(u32, Vec<u8>)> {
+        Ok(match table {
+            Table::Gdt => (0, self.state.lock().unwrap().gdt.clone()),
+            Table::Ldt => (0, Vec::new()),
+            Table::Idt => (0, self.state.lock().unwrap().mem[..IVT_SIZE].to_vec()),
+        })
+    }
+
+    fn descriptor(&self, selector: u16) -> Result<u64> {
+        let table = if selector & 4 != 0 {
+            Table::Ldt
+        } else {
+            Table::Gdt
+        };
+
+        let (_, data) = self.descriptor_table(table)?;
+        let index = usize::from(selector & !7);
+
+        match data.get(index..index + 8) {
+            Some(raw) => Ok(u64::from_le_bytes(raw.try_into().unwrap())),
+            None => bail!("Selector {selector:04X} is beyond the {table}."),
+        }
+    }
+
+    fn step_in(&self) -> Result<u32> {
+        let mut st = self.state.lock().unwrap();
+        st.execute();
+        st.watch_hit = None;
+
+        Ok(st.regs.eip)
+    }
+
+    fn run(&self) -> Result<u32> {
+        let mut st = self.state.lock().unwrap();
+        let eip = st.regs.eip;
+        st.watch_hit = None;
+
+        // the instruction the CPU stopped on is not reported twice, as in DOSBox
+        for _ in 0..RUN_LIMIT {
+            let prev = Address::Real(st.regs.cs, st.regs.eip);
+            st.execute();
+
+            let ip = Address::Real(st.regs.cs, st.regs.eip);
+
+            if let Some(hit) = st.watch_changed(prev) {
+                st.watch_hit = Some(hit);
+
+                break;
+            }
+
+            if let Some(i) = st.breakpoints.iter().position(|(k, _)| st.stops_at(*k, ip)) {
+                if st.breakpoints[i].1 {
+                    st.breakpoints.remove(i);
+                }
+
+                break;
+            }
+
+            if st.mem[real(st.regs.cs, st.regs.eip)] == 0xCC {
+                break;
+            }
+        }
+
+        drop(st);
+        self.notify_break();
+
+        Ok(eip)
+    }
+
+    fn break_in(&self) -> Result<u32> {
+        // never running for long, so always stopped already
+        self.notify_break();
+
+        Ok(self.state.lock().unwrap().regs.eip)
+    }
+
+    fn on_break(&self, callback: Box<dyn Fn() + Send>) -> Result<()> {
+        *self.on_break.lock().unwrap() = Some(callback);
+
+        Ok(())
+    }
+
+    fn add_breakpoint(&self, kind: Kind, once: bool) -> Result<()> {
+        let mut st = self.state.lock().unwrap();
+
+        if st.breakpoints.iter().any(|(k, _)| *k == kind)
+            || st.watches.iter().any(|(k, _)| *k == kind)
+        {
+            bail!("Breakpoint {kind} is already set.");
+        }
+
+        match kind {
+            Kind::Code(_) | Kind::Int { .. } => st.breakpoints.push((kind, once)),
+            _ => {
+                let value = st.watched(kind);
+                st.watches.push((kind, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn remove_breakpoint(&self, kind: Kind) -> Result<()> {
+        let mut st = self.state.lock().unwrap();
+        st.breakpoints.retain(|(k, _)| *k != kind);
+        st.watches.retain(|(k, _)| *k != kind);
+
+        Ok(())
+    }
+
+    fn watch_hit(&self) -> Result<Option<WatchHit>> {
+        Ok(self.state.lock().unwrap().watch_hit)
+    }
+
+    fn regs(&self) -> Result<Regs> {
+        Ok(self.state.lock().unwrap().regs)
+    }
+
+    fn set_reg(&self, name: &str, value: u32) -> Result<()> {
+        if !set_register(&mut self.state.lock().unwrap().regs, name, value) {
+            bail!("Unknown register `{name}`.");
+        }
+
+        Ok(())
+    }
+
+    fn fpu(&self) -> Result<FpuRegs> {
+        // as FNINIT leaves it, nothing ever runs on the FPU here
+        Ok(FpuRegs {
+            cw: 0x037F,
+            sw: 0,
+            tw: 0xFFFF,
+            regs: vec![0; 80],
+        })
+    }
+
+    fn read_mem(&self, addr: Address, length: u32) -> Result<Vec<u8>> {
+        let st = self.state.lock().unwrap();
+
+        (0..length.min(READ_LIMIT))
+            .map(|i| Ok(st.mem[linear(addr.with_offset(addr.offset().wrapping_add(i)))?]))
+            .collect()
+    }
+
+    fn write_mem(&self, addr: Address, value: u8) -> Result<u8> {
+        let mut st = self.state.lock().unwrap();
+
+        Ok(std::mem::replace(&mut st.mem[linear(addr)?], value))
+    }
+}
+
+fn real(segment: u16, offset: u32) -> usize {
+    (((segment as usize) << 4) + offset as usize) & (MEMORY_SIZE - 1)
+}
+
+/// Index of the byte in memory, paging is always off and selectors are unknown in real mode.
+fn linear(addr: Address) -> Result<usize> {
+    Ok(match addr {
+        Address::Real(segment, offset) => real(segment, offset),
+        Address::Linear(addr) | Address::Physical(addr) => addr as usize & (MEMORY_SIZE - 1),
+        Address::Prot(..) => bail!("Selector {addr} needs protected mode."),
+    })
+}
+
+fn mem_watch(segment: u16, offset: u32, watch: u8) -> fdo::Result<Kind> {
+    match mem_address(watch, segment, offset) {
+        Some(addr @ (Address::Real(..) | Address::Prot(..) | Address::Linear(_))) => {
+            Ok(Kind::Write(addr))
+        }
+        _ => Err(fdo::Error::InvalidArgs(format!(
+            "Unknown watch type {watch}."
+        ))),
+    }
+}
+
+fn mem_addr(mode: u8, segment: u16, offset: u32) -> fdo::Result<Address> {
+    mem_address(mode, segment, offset)
+        .ok_or_else(|| fdo::Error::InvalidArgs(format!("Unknown memory mode {mode}.")))
+}
+
+fn failed(e: anyhow::Error) -> fdo::Error {
+    fdo::Error::Failed(e.to_string())
+}
+
+struct Cpu(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl Cpu {
+    #[dbus_interface(name = "get")]
+    fn get(&self) -> fdo::Result<CpuMode> {
+        self.0.cpu_mode().map_err(failed)
+    }
+
+    #[dbus_interface(name = "control")]
+    fn control(&self) -> fdo::Result<ControlRegs> {
+        self.0.control_regs().map_err(failed)
+    }
+
+    #[dbus_interface(name = "callback_info")]
+    fn callback_info(&self, _index: u16) -> String {
+        String::new()
+    }
+
+    #[dbus_interface(name = "step_in")]
+    fn step_in(&self) -> fdo::Result<u32> {
+        self.0.step_in().map_err(failed)
+    }
+
+    #[dbus_interface(name = "run")]
+    async fn run(&self, #[zbus(connection)] conn: &zbus::Connection) -> fdo::Result<u32> {
+        let eip = self.0.run().map_err(failed)?;
+        notify_break(conn).await?;
+
+        Ok(eip)
+    }
+
+    #[dbus_interface(name = "break")]
+    async fn break_in(&self, #[zbus(connection)] conn: &zbus::Connection) -> fdo::Result<u32> {
+        let eip = self.0.regs().map_err(failed)?.eip;
+        notify_break(conn).await?;
+
+        Ok(eip)
+    }
+}
+
+/// Announces a stop the way DOSBox does, not caring whether anyone listens.
+async fn notify_break(conn: &zbus::Connection) -> zbus::Result<()> {
+    let msg = MessageBuilder::method_call("/", "breakNow")?
+        .destination("com.dosbox.dbg")?
+        .interface("com.dosbox.dbg")?
+        .with_flags(MessageFlags::NoReplyExpected)?
+        .build(&())?;
+    conn.send_message(msg).await?;
+
+    Ok(())
+}
+
+struct Descriptors(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl Descriptors {
+    #[dbus_interface(name = "get")]
+    fn get(&self, table: u8) -> fdo::Result<(u32, Vec<u8>)> {
+        let table = match table {
+            0 => Table::Gdt,
+            1 => Table::Ldt,
+            2 => Table::Idt,
+            _ => return Err(fdo::Error::InvalidArgs(format!("Unknown table {table}."))),
+        };
+
+        self.0.descriptor_table(table).map_err(failed)
+    }
+
+    #[dbus_interface(name = "lookup")]
+    fn lookup(&self, selector: u16) -> fdo::Result<u64> {
+        self.0.descriptor(selector).map_err(failed)
+    }
+}
+
+struct CpuRegs(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl CpuRegs {
+    #[dbus_interface(name = "get")]
+    fn get(&self) -> fdo::Result<Regs> {
+        self.0.regs().map_err(failed)
+    }
+
+    #[dbus_interface(name = "set")]
+    fn set(&self, name: &str, value: u32) -> bool {
+        self.0.set_reg(name, value).is_ok()
+    }
+}
+
+struct Fpu(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl Fpu {
+    #[dbus_interface(name = "get")]
+    fn get(&self) -> fdo::Result<FpuRegs> {
+        self.0.fpu().map_err(failed)
+    }
+}
+
+struct Memory(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl Memory {
+    #[dbus_interface(name = "get")]
+    fn get(&self, segment: u16, offset: u32, length: u32) -> fdo::Result<Vec<u8>> {
+        self.0
+            .read_mem(Address::Real(segment, offset), length)
+            .map_err(failed)
+    }
+
+    #[dbus_interface(name = "set")]
+    fn set(&self, segment: u16, offset: u32, value: u8) -> fdo::Result<u8> {
+        self.0
+            .write_mem(Address::Real(segment, offset), value)
+            .map_err(failed)
+    }
+
+    #[dbus_interface(name = "read")]
+    fn read(&self, mode: u8, segment: u16, offset: u32, length: u32) -> fdo::Result<Vec<u8>> {
+        self.0
+            .read_mem(mem_addr(mode, segment, offset)?, length)
+            .map_err(failed)
+    }
+
+    #[dbus_interface(name = "write")]
+    fn write(&self, mode: u8, segment: u16, offset: u32, value: u8) -> fdo::Result<u8> {
+        self.0
+            .write_mem(mem_addr(mode, segment, offset)?, value)
+            .map_err(failed)
+    }
+}
+
+struct Breakpoints(Arc<Machine>);
+
+#[dbus_interface(name = "com.dosbox")]
+impl Breakpoints {
+    #[dbus_interface(name = "add")]
+    fn add(&self, segment: u16, offset: u32, once: bool) -> bool {
+        self.0
+            .add_breakpoint(Kind::Code(Address::Real(segment, offset)), once)
+            .is_ok()
+    }
+
+    #[dbus_interface(name = "remove")]
+    fn remove(&self, segment: u16, offset: u32) -> fdo::Result<bool> {
+        self.0
+            .remove_breakpoint(Kind::Code(Address::Real(segment, offset)))
+            .map(|_| true)
+            .map_err(failed)
+    }
+
+    #[dbus_interface(name = "add_int")]
+    fn add_int(&self, num: u8, ah: u16, once: bool) -> fdo::Result<bool> {
+        let kind = Kind::Int {
+            num,
+            ah: u8::try_from(ah).ok(),
+        };
+
+        // replaces an existing one like DOSBox does
+        self.0.remove_breakpoint(kind).map_err(failed)?;
+        self.0.add_breakpoint(kind, once).map_err(failed)?;
+
+        Ok(true)
+    }
+
+    #[dbus_interface(name = "remove_int")]
+    fn remove_int(&self, num: u8, ah: u16) -> fdo::Result<bool> {
+        let kind = Kind::Int {
+            num,
+            ah: u8::try_from(ah).ok(),
+        };
+
+        self.0.remove_breakpoint(kind).map(|_| true).map_err(failed)
+    }
+
+    #[dbus_interface(name = "add_mem")]
+    fn add_mem(&self, segment: u16, offset: u32, watch: u8) -> fdo::Result<bool> {
+        let kind = mem_watch(segment, offset, watch)?;
+
+        // replaces an existing one like DOSBox does
+        self.0.remove_breakpoint(kind).map_err(failed)?;
+        self.0.add_breakpoint(kind, false).map_err(failed)?;
+
+        Ok(true)
+    }
+
+    #[dbus_interface(name = "remove_mem")]
+    fn remove_mem(&self, segment: u16, offset: u32, watch: u8) -> fdo::Result<bool> {
+        let kind = mem_watch(segment, offset, watch)?;
+
+        self.0.remove_breakpoint(kind).map(|_| true).map_err(failed)
+    }
+
+    #[dbus_interface(name = "watch_hit")]
+    fn watch_hit(&self) -> fdo::Result<RawWatchHit> {
+        let hit = self.0.watch_hit().map_err(failed)?;
+
+        Ok(match hit {
+            Some(WatchHit {
+                kind,
+                old,
+                new,
+                writer,
+            }) => {
+                let (segment, offset, watch) = match kind {
+                    Kind::Write(addr) => watch_args(addr).map_err(failed)?,
+                    _ => (0, 0, 0),
+                };
+
+                (
+                    true,
+                    watch,
+                    segment,
+                    offset,
+                    old,
+                    new,
+                    writer.segment().unwrap_or_default(),
+                    writer.offset(),
+                )
+            }
+            None => Default::default(),
+        })
+    }
+}
+
+/// Registers `com.dosbox` on the session bus and serves it until the connection is dropped.
+///
+/// `image` is an optional file loaded at `1000:0100` with all segment registers pointing to it.
+pub fn serve(image: Option<String>) -> Result<Connection> {
+    let image = match image {
+        Some(path) => fs::read(path)?,
+        None => Vec::new(),
+    };
+
+    serve_on(ConnectionBuilder::session()?, Machine::new(&image))
+}
+
+/// Registers `com.dosbox` for the machine on the bus the builder connects to, e.g. a private one.
+pub fn serve_on(builder: ConnectionBuilder<'_>, machine: Machine) -> Result<Connection> {
+    let machine = Arc::new(machine);
+
+    let conn = builder
+        .name("com.dosbox")?
+        .serve_at("/cpu", Cpu(machine.clone()))?
+        .serve_at("/cpu/desc", Descriptors(machine.clone()))?
+        .serve_at("/cpu/regs", CpuRegs(machine.clone()))?
+        .serve_at("/fpu", Fpu(machine.clone()))?
+        .serve_at("/mem", Memory(machine.clone()))?
+        .serve_at("/bp", Breakpoints(machine))?
+        .build()?;
+
+    Ok(conn)
+}
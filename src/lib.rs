@@ -0,0 +1,6 @@
+pub mod breakpoints;
+pub mod bus;
+pub mod fake;
+pub mod target;
+pub mod tui;
+pub mod x86;
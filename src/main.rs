@@ -2,14 +2,20 @@ use anyhow::Result;
 use zbus::blocking::Connection;
 use zi::prelude::*;
 
-mod bus;
-mod tui;
-mod x86;
+use std::rc::Rc;
 
-use crate::{bus::Proxy, tui::debugbox::DebugBox};
+use debugbox::{bus::Proxy, fake, tui::debugbox::DebugBox};
 
 fn main() -> Result<()> {
-    let app = DebugBox::with(Proxy::new(&Connection::session()?)?);
+    let mut args = std::env::args().skip(1);
+
+    // `--fake [image]` serves a stand-in DOSBox from this very process
+    let _fake = match args.next().as_deref() {
+        Some("--fake") => Some(fake::serve(args.next())?),
+        _ => None,
+    };
+
+    let app = DebugBox::with(Rc::new(Proxy::new(&Connection::session()?)?));
 
     zi_term::incremental()?.run_event_loop(app)?;
 
@@ -0,0 +1,59 @@
+use anyhow::Result;
+
+use crate::{
+    breakpoints::{Kind, WatchHit},
+    bus::{ControlRegs, CpuMode, FpuRegs, Regs},
+    x86::{desc::Table, Address},
+};
+
+/// A debuggee the UI and the decoder can drive.
+///
+/// `bus::Proxy` implements it on top of the DOSBox D-Bus service, but any backend
+/// able to stop, step and expose memory and registers will do.
+pub trait Target {
+    /// Returns protected mode flag and default sizes of the code and stack segments.
+    fn cpu_mode(&self) -> Result<CpuMode>;
+
+    /// Returns CR0, CR2, CR3 and CR4, paging is on when CR0.PG is set.
+    fn control_regs(&self) -> Result<ControlRegs>;
+
+    /// Returns the linear base and the contents of a descriptor table, in real mode the IDT
+    /// is the interrupt vector table and the others are empty.
+    fn descriptor_table(&self, table: Table) -> Result<(u32, Vec<u8>)>;
+
+    /// Returns the raw descriptor a selector refers to in the GDT or the LDT.
+    fn descriptor(&self, selector: u16) -> Result<u64>;
+
+    /// Executes a single instruction and returns the new EIP.
+    fn step_in(&self) -> Result<u32>;
+
+    /// Resumes execution, `on_break` tells when the target stops again.
+    fn run(&self) -> Result<u32>;
+
+    /// Asks the running target to stop, `on_break` tells when it did.
+    fn break_in(&self) -> Result<u32>;
+
+    /// Registers a callback invoked whenever the running target stops.
+    fn on_break(&self, callback: Box<dyn Fn() + Send>) -> Result<()>;
+
+    /// Arms a breakpoint, a one-shot one is dropped by the target once hit.
+    fn add_breakpoint(&self, kind: Kind, once: bool) -> Result<()>;
+
+    fn remove_breakpoint(&self, kind: Kind) -> Result<()>;
+
+    /// Tells which watched byte changed when a watchpoint caused the last stop.
+    fn watch_hit(&self) -> Result<Option<WatchHit>>;
+
+    fn regs(&self) -> Result<Regs>;
+
+    /// Writes a register or a flag by its name, e.g. `eax`, `cs`, `eip`, `zf` or `efl`.
+    fn set_reg(&self, name: &str, value: u32) -> Result<()>;
+
+    fn fpu(&self) -> Result<FpuRegs>;
+
+    /// Reads up to 64 KiB, it fails if any of the bytes can't be read.
+    fn read_mem(&self, addr: Address, length: u32) -> Result<Vec<u8>>;
+
+    /// Writes a byte and returns the previous value.
+    fn write_mem(&self, addr: Address, value: u8) -> Result<u8>;
+}
@@ -0,0 +1,196 @@
+use zi::{prelude::*, Callback};
+
+use crate::{breakpoints::Breakpoints as List, tui::PaneStatus};
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub breakpoints: List,
+    pub on_remove: Callback<usize>,
+    pub on_enable: Callback<(usize, bool)>,
+    pub on_condition: Callback<usize>,
+    pub on_count: Callback<usize>,
+    pub on_add_int: Callback<()>,
+}
+
+pub struct Breakpoints {
+    props: Properties,
+    frame: Rect,
+    skip: usize,
+    pos: usize,
+}
+
+pub enum Message {
+    Up,
+    Down,
+    Toggle,
+    Remove,
+    Condition,
+    Count,
+    AddInt,
+}
+
+impl Component for Breakpoints {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        Self {
+            props,
+            frame,
+            skip: 0,
+            pos: 0,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        let changed =
+            self.props.status != props.status || self.props.breakpoints != props.breakpoints;
+
+        self.props = props;
+
+        let len = self.props.breakpoints.len();
+
+        if self.skip + self.pos >= len {
+            self.skip = self.skip.min(len.saturating_sub(1));
+            self.pos = len.saturating_sub(self.skip + 1);
+        }
+
+        changed.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        let prev = (self.skip, self.pos);
+        let index = self.skip + self.pos;
+
+        match message {
+            Message::Up if self.pos > 0 => self.pos -= 1,
+            Message::Up if self.skip > 0 => self.skip -= 1,
+            Message::Down if index + 1 < self.props.breakpoints.len() => {
+                if self.pos + 1 < self.frame.height() {
+                    self.pos += 1;
+                } else {
+                    self.skip += 1;
+                }
+            }
+            Message::Toggle => {
+                if let Some(bp) = self.props.breakpoints.iter().nth(index) {
+                    self.props.on_enable.emit((index, !bp.enabled));
+                }
+            }
+            Message::Remove if index < self.props.breakpoints.len() => {
+                self.props.on_remove.emit(index);
+            }
+            Message::Condition if index < self.props.breakpoints.len() => {
+                self.props.on_condition.emit(index);
+            }
+            Message::Count if index < self.props.breakpoints.len() => {
+                self.props.on_count.emit(index);
+            }
+            Message::AddInt => self.props.on_add_int.emit(()),
+            _ => (),
+        }
+
+        ((self.skip, self.pos) != prev).into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("up", || Message::Up).with([Key::Up]);
+        bind.command("down", || Message::Down).with([Key::Down]);
+        bind.command("toggle", || Message::Toggle)
+            .with([Key::Char(' ')]);
+        bind.command("remove", || Message::Remove)
+            .with([Key::Delete]);
+        bind.command("condition", || Message::Condition)
+            .with([Key::Char('c')]);
+        bind.command("hit-count", || Message::Count)
+            .with([Key::Char('h')]);
+        bind.command("int-breakpoint", || Message::AddInt)
+            .with([Key::Char('i')]);
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        for (y, bp) in self
+            .props
+            .breakpoints
+            .iter()
+            .skip(self.skip)
+            .take(self.frame.size.height)
+            .enumerate()
+        {
+            let (mut mark_st, mut addr_st, mut text_st) = if bp.enabled {
+                (super::ST_BREAKPOINT, super::ST_NORMAL, super::ST_CAPTION)
+            } else {
+                (super::ST_CAPTION, super::ST_CAPTION, super::ST_CAPTION)
+            };
+
+            if self.props.status.focused && self.pos == y {
+                mark_st.background = super::ST_SELECTED.background;
+                addr_st.background = super::ST_SELECTED.background;
+                text_st.background = super::ST_SELECTED.background;
+
+                canvas.clear_region(
+                    Rect::new(Position::new(0, y), Size::new(self.frame.size.width, 1)),
+                    text_st,
+                );
+            }
+
+            canvas.draw_str(
+                0,
+                y,
+                mark_st,
+                if bp.enabled {
+                    super::BP_ENABLED
+                } else {
+                    super::BP_DISABLED
+                },
+            );
+            let kind = bp.kind.to_string();
+            canvas.draw_str(2, y, addr_st, &kind);
+
+            let mut text = Vec::new();
+
+            if bp.once {
+                text.push("once".to_owned());
+            }
+
+            if let Some(c) = &bp.condition {
+                text.push(format!("if {}", c.text));
+            }
+
+            match bp.count {
+                Some(n) => text.push(format!("hits {}/{n}", bp.hits)),
+                None if bp.hits > 0 => text.push(format!("hits {}", bp.hits)),
+                None => (),
+            }
+
+            let text = text.join(" ");
+            let x = (kind.chars().count() + 3).max(12);
+            let width = self.frame.size.width.saturating_sub(x);
+
+            canvas.draw_str(
+                x,
+                y,
+                text_st,
+                &text.chars().take(width).collect::<String>(),
+            );
+        }
+
+        canvas.into()
+    }
+}
@@ -0,0 +1,233 @@
+use anyhow::{anyhow, Error};
+use iced_x86::{Formatter, IntelFormatter};
+use std::rc::Rc;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+    Callback,
+};
+
+use crate::{
+    bus::Regs,
+    target::Target,
+    tui::{PaneStatus, Scroll},
+    x86::{
+        dec::{call_stack, CallKind, Frame},
+        Address,
+    },
+};
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+    pub regs: Regs,
+    /// Shows the code at the return site of the frame.
+    pub on_select: Callback<Address>,
+    pub scroll: Scroll,
+    pub on_scroll: Callback<Scroll>,
+}
+
+impl PartialEq for Properties {
+    fn eq(&self, other: &Properties) -> bool {
+        self.status == other.status && self.regs == other.regs
+    }
+}
+
+/// Callers of the current routine, the innermost first.
+pub struct Calls {
+    props: Properties,
+    frame: Rect,
+    error: Option<Error>,
+    frames: Vec<Frame>,
+    skip: usize,
+    pos: usize,
+}
+
+pub enum Message {
+    Up,
+    Down,
+    Select,
+}
+
+impl Calls {
+    fn reload(&mut self) {
+        let result = if !self.props.status.attached {
+            Err(anyhow!("Not attached."))
+        } else {
+            call_stack(&*self.props.target, &self.props.regs)
+        };
+
+        match result {
+            Ok(f) => {
+                self.frames = f;
+                self.error = None;
+            }
+            Err(e) => {
+                self.frames.clear();
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl Component for Calls {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let Scroll { skip, pos } = props.scroll;
+        let mut calls = Self {
+            props,
+            frame,
+            error: None,
+            frames: Vec::new(),
+            skip,
+            pos,
+        };
+
+        calls.reload();
+
+        if skip + pos >= calls.frames.len() {
+            calls.skip = 0;
+            calls.pos = 0;
+        }
+
+        calls
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false.into();
+        }
+
+        let reload = self.props.regs != props.regs || props.status.reload;
+        self.props = props;
+
+        if reload {
+            self.reload();
+            self.skip = 0;
+            self.pos = 0;
+        }
+
+        true.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        let prev = (self.skip, self.pos);
+        let index = self.skip + self.pos;
+
+        match message {
+            Message::Up if self.pos > 0 => self.pos -= 1,
+            Message::Up if self.skip > 0 => self.skip -= 1,
+            Message::Down if index + 1 < self.frames.len() => {
+                if self.pos + 1 < self.frame.height() {
+                    self.pos += 1;
+                } else {
+                    self.skip += 1;
+                }
+            }
+            Message::Select => {
+                if let Some(f) = self.frames.get(index) {
+                    self.props.on_select.emit(f.ret);
+                }
+            }
+            _ => (),
+        }
+
+        if (self.skip, self.pos) == prev {
+            return false.into();
+        }
+
+        self.props.on_scroll.emit(Scroll {
+            skip: self.skip,
+            pos: self.pos,
+        });
+
+        true.into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("up", || Message::Up).with([Key::Up]);
+        bind.command("down", || Message::Down).with([Key::Down]);
+        bind.command("select", || Message::Select)
+            .with([Key::Char('\n')]);
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        if let Some(e) = &self.error {
+            return Text::with(
+                TextProperties::new()
+                    .style(super::ST_NORMAL)
+                    .align(TextAlign::Centre)
+                    .content(e.to_string()),
+            );
+        }
+
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        let mut fmt = IntelFormatter::new();
+        fmt.options_mut().set_space_after_operand_separator(true);
+
+        // protected mode addresses are wider than real mode ones
+        let addr_width = self
+            .frames
+            .iter()
+            .map(|f| f.ret.to_string().len())
+            .max()
+            .unwrap_or_default();
+        let kind_x = 3 + addr_width + 1;
+
+        for (y, (i, f)) in self
+            .frames
+            .iter()
+            .enumerate()
+            .skip(self.skip)
+            .take(self.frame.size.height)
+            .enumerate()
+        {
+            let (mut num_st, mut addr_st, mut kind_st) =
+                (super::ST_CAPTION, super::ST_NORMAL, super::ST_CAPTION);
+
+            if self.props.status.focused && self.pos == y {
+                num_st.background = super::ST_SELECTED.background;
+                addr_st.background = super::ST_SELECTED.background;
+                kind_st.background = super::ST_SELECTED.background;
+
+                canvas.clear_region(
+                    Rect::new(Position::new(0, y), Size::new(self.frame.size.width, 1)),
+                    addr_st,
+                );
+            }
+
+            let kind = match f.kind {
+                CallKind::Near => "near",
+                CallKind::Far => "far",
+                CallKind::Interrupt => "int",
+            };
+
+            canvas.draw_str(0, y, num_st, &format!("{:>2}", i + 1));
+            canvas.draw_str(3, y, addr_st, &f.ret.to_string());
+            canvas.draw_str(kind_x, y, kind_st, kind);
+
+            let mut call = String::new();
+            fmt.format(&f.call, &mut call);
+            canvas.draw_str(kind_x + 5, y, addr_st, &call);
+        }
+
+        canvas.into()
+    }
+}
@@ -4,10 +4,12 @@ use std::rc::Rc;
 use zi::{
     components::text::{Text, TextAlign, TextProperties},
     prelude::*,
+    Callback,
 };
 
 use crate::{
-    bus::Proxy,
+    breakpoints::Breakpoints,
+    target::Target,
     tui::PaneStatus,
     x86::{
         dec::{fetch_after, fetch_before},
@@ -15,16 +17,25 @@ use crate::{
     },
 };
 
+const ADDR_LEN: usize = 9; // seg:off the columns are laid out for
+
 #[derive(Clone)]
 pub struct Properties {
     pub status: PaneStatus,
-    pub proxy: Rc<Proxy>,
+    pub target: Rc<dyn Target>,
+    /// Where the code is shown from.
     pub addr: Address,
+    /// The current instruction.
+    pub ip: Address,
+    pub breakpoints: Breakpoints,
+    pub on_toggle_breakpoint: Callback<Address>,
+    pub on_run_to: Callback<Address>,
+    pub on_set_ip: Callback<Address>,
 }
 
 impl PartialEq for Properties {
     fn eq(&self, other: &Properties) -> bool {
-        self.status == other.status && self.addr == other.addr
+        self.status == other.status && self.addr == other.addr && self.ip == other.ip
     }
 }
 
@@ -42,6 +53,9 @@ pub enum Message {
     Down,
     Enter,
     Escape,
+    ToggleBreakpoint,
+    RunToCursor,
+    SetIp,
 }
 
 impl Component for Code {
@@ -52,7 +66,7 @@ impl Component for Code {
         let (code, error) = if !props.status.attached {
             (Vec::new(), Some(anyhow!("Not attached.")))
         } else {
-            match fetch_after(&props.proxy, props.addr, frame.size.height) {
+            match fetch_after(&*props.target, props.addr, frame.size.height) {
                 Ok(c) => (c, None),
                 Err(e) => (Vec::new(), Some(e)),
             }
@@ -70,7 +84,11 @@ impl Component for Code {
 
     fn change(&mut self, props: Self::Properties) -> ShouldRender {
         if self.props == props {
-            return false.into();
+            // breakpoint markers don't need the code to be fetched again
+            let changed = self.props.breakpoints != props.breakpoints;
+            self.props = props;
+
+            return changed.into();
         }
 
         if !props.status.attached {
@@ -90,7 +108,7 @@ impl Component for Code {
             .enumerate()
             .map(|(i, (ins, _))| (i, ins.ip32()))
             .find_map(|(i, ip)| {
-                if ip == props.addr.offset {
+                if ip == props.addr.offset() {
                     self.code
                         .get(self.skip + i.saturating_sub(pad))
                         .map(|(x, _)| x.ip32())
@@ -98,9 +116,9 @@ impl Component for Code {
                     None
                 }
             })
-            .unwrap_or(props.addr.offset);
+            .unwrap_or(props.addr.offset());
 
-        match fetch_after(&props.proxy, (props.addr.segment, start).into(), limit) {
+        match fetch_after(&*props.target, props.addr.with_offset(start), limit) {
             Ok(c) => {
                 self.code = c;
                 self.skip = 0;
@@ -134,8 +152,8 @@ impl Component for Code {
             Message::Up => {
                 if let Some(offset) = self.code.first().map(|(i, _)| i.ip32()) {
                     match fetch_before(
-                        &self.props.proxy,
-                        (self.props.addr.segment, offset).into(),
+                        &*self.props.target,
+                        self.props.addr.with_offset(offset),
                         self.frame.size.height,
                     ) {
                         Ok(mut c) if !c.is_empty() => {
@@ -166,8 +184,8 @@ impl Component for Code {
             Message::Down => {
                 if let Some(offset) = self.code.last().map(|(i, _)| i.next_ip32()) {
                     match fetch_after(
-                        &self.props.proxy,
-                        (self.props.addr.segment, offset).into(),
+                        &*self.props.target,
+                        self.props.addr.with_offset(offset),
                         self.frame.size.height,
                     ) {
                         Ok(c) if !c.is_empty() => {
@@ -189,6 +207,35 @@ impl Component for Code {
                     self.pos = None;
                 }
             }
+            Message::ToggleBreakpoint => {
+                let offset = match self.pos {
+                    Some(pos) => self.code.get(self.skip + pos).map(|(i, _)| i.ip32()),
+                    None => Some(self.props.addr.offset()),
+                };
+
+                if let Some(offset) = offset {
+                    self.props
+                        .on_toggle_breakpoint
+                        .emit(self.props.addr.with_offset(offset));
+                }
+            }
+            Message::RunToCursor | Message::SetIp => {
+                let Some(offset) = self
+                    .pos
+                    .and_then(|pos| self.code.get(self.skip + pos))
+                    .map(|(i, _)| i.ip32())
+                else {
+                    return false.into();
+                };
+
+                let addr = self.props.addr.with_offset(offset);
+
+                if matches!(message, Message::RunToCursor) {
+                    self.props.on_run_to.emit(addr);
+                } else {
+                    self.props.on_set_ip.emit(addr);
+                }
+            }
         }
 
         ((self.skip, self.pos, self.code.len()) != prev).into()
@@ -206,6 +253,12 @@ impl Component for Code {
         bind.command("enter", || Message::Enter)
             .with([Key::Char('\n')]);
         bind.command("escape", || Message::Escape).with([Key::Esc]);
+        bind.command("toggle-breakpoint", || Message::ToggleBreakpoint)
+            .with([Key::F(9)]);
+        bind.command("run-to-cursor", || Message::RunToCursor)
+            .with([Key::F(4)]);
+        bind.command("set-next-statement", || Message::SetIp)
+            .with([Key::Ctrl('n')]);
     }
 
     fn resize(&mut self, frame: Rect) -> ShouldRender {
@@ -230,6 +283,8 @@ impl Component for Code {
         let mut fmt = IntelFormatter::new();
         fmt.options_mut().set_space_after_operand_separator(true);
         let mut out = String::new();
+        // selector:offset is wider than seg:off
+        let pad = self.props.addr.to_string().len().saturating_sub(ADDR_LEN);
 
         for (y, (ins, data)) in self
             .code
@@ -238,7 +293,9 @@ impl Component for Code {
             .take(self.frame.size.height)
             .enumerate()
         {
-            let (mut addr_st, mut code_st) = if ins.ip32() == self.props.addr.offset {
+            let ip = self.props.addr.with_offset(ins.ip32());
+
+            let (mut addr_st, mut code_st) = if self.props.ip == ip {
                 (super::ST_ACTIVE, super::ST_ACTIVE)
             } else {
                 (super::ST_CAPTION, super::ST_NORMAL)
@@ -254,14 +311,33 @@ impl Component for Code {
                 );
             }
 
+            canvas.draw_str(0, y, addr_st, &ip.to_string());
+            if let Some(bp) = self
+                .props
+                .breakpoints
+                .get(self.props.addr.with_offset(ins.ip32()))
+            {
+                let mut st = if bp.enabled {
+                    super::ST_BREAKPOINT
+                } else {
+                    super::ST_CAPTION
+                };
+                st.background = code_st.background;
+
+                canvas.draw_str(
+                    10 + pad,
+                    y,
+                    st,
+                    if bp.enabled {
+                        super::BP_ENABLED
+                    } else {
+                        super::BP_DISABLED
+                    },
+                );
+            }
+
             canvas.draw_str(
-                0,
-                y,
-                addr_st,
-                &format!("{:04X}:{:04X}", self.props.addr.segment, ins.ip16()),
-            );
-            canvas.draw_str(
-                12,
+                12 + pad,
                 y,
                 code_st,
                 &data
@@ -271,12 +347,12 @@ impl Component for Code {
 
             out.clear();
             fmt.format_mnemonic(ins, &mut out);
-            canvas.draw_str(30, y, code_st, &out);
+            canvas.draw_str(30 + pad, y, code_st, &out);
 
             if ins.op_count() > 0 {
                 out.clear();
                 fmt.format_all_operands(ins, &mut out);
-                canvas.draw_str(42, y, code_st, &out);
+                canvas.draw_str(42 + pad, y, code_st, &out);
             }
         }
 
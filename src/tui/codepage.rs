@@ -0,0 +1,81 @@
+/// Upper halves of the code pages, non-breaking and soft hyphen are shown as a space and `-`.
+const CP437: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅ",
+    "ÉæÆôöòûùÿÖÜ¢£¥₧ƒ",
+    "áíóúñÑªº¿⌐¬½¼¡«»",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧",
+    "╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "αßΓπΣσµτΦΘΩδ∞φε∩",
+    "≡±≥≤⌠⌡÷≈°∙·√ⁿ²■ ",
+);
+
+const CP850: &str = concat!(
+    "ÇüéâäàåçêëèïîìÄÅ",
+    "ÉæÆôöòûùÿÖÜø£Ø×ƒ",
+    "áíóúñÑªº¿®¬½¼¡«»",
+    "░▒▓│┤ÁÂÀ©╣║╗╝¢¥┐",
+    "└┴┬├─┼ãÃ╚╔╩╦╠═╬¤",
+    "ðÐÊËÈıÍÎÏ┘┌█▄¦Ì▀",
+    "ÓßÔÒõÕµþÞÚÛÙýÝ¯´",
+    "-±‗¾¶§÷¸°¨·¹³²■ ",
+);
+
+const CP866: &str = concat!(
+    "АБВГДЕЖЗИЙКЛМНОП",
+    "РСТУФХЦЧШЩЪЫЬЭЮЯ",
+    "абвгдежзийклмноп",
+    "░▒▓│┤╡╢╖╕╣║╗╝╜╛┐",
+    "└┴┬├─┼╞╟╚╔╩╦╠═╬╧",
+    "╨╤╥╙╘╒╓╫╪┘┌█▄▌▐▀",
+    "рстуфхцчшщъыьэюя",
+    "ЁёЄєЇїЎў°∙·√№¤■ ",
+);
+
+/// Glyphs the video BIOS shows for control characters, the same in all code pages.
+const CONTROL: &str = " ☺☻♥♦♣♠•◘○◙♂♀♪♫☼►◄↕‼¶§▬↨↑↓→←∟↔▲▼";
+
+/// Code page the text in memory is decoded with.
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum CodePage {
+    #[default]
+    Cp437,
+    Cp850,
+    Cp866,
+}
+
+impl CodePage {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Cp437 => Self::Cp850,
+            Self::Cp850 => Self::Cp866,
+            Self::Cp866 => Self::Cp437,
+        }
+    }
+
+    fn upper(self) -> &'static str {
+        match self {
+            Self::Cp437 => CP437,
+            Self::Cp850 => CP850,
+            Self::Cp866 => CP866,
+        }
+    }
+
+    pub fn decode(self, b: u8) -> char {
+        match b {
+            0x00..=0x1F => CONTROL.chars().nth(b.into()),
+            0x20..=0x7E => Some(char::from(b)),
+            0x7F => Some('⌂'),
+            _ => self.upper().chars().nth(usize::from(b - 0x80)),
+        }
+        .unwrap_or(' ')
+    }
+
+    /// Finds the byte a typed character is stored as, control glyphs aren't typed in.
+    pub fn encode(self, c: char) -> Option<u8> {
+        match c {
+            ' '..='~' => Some(c as u8),
+            _ => (0x80..=0xFF).find(|b| self.decode(*b) == c),
+        }
+    }
+}
@@ -3,18 +3,131 @@ use std::rc::Rc;
 use zi::{
     components::text::{Text, TextAlign, TextProperties},
     prelude::*,
+    AnyCharacter, Callback,
 };
 
-use crate::{bus::Proxy, tui::PaneStatus, x86::Address};
+use crate::{
+    target::Target,
+    tui::{codepage::CodePage, PaneStatus},
+    x86::{
+        fpu::{real80, REAL80_WIDTH},
+        Address,
+    },
+};
+
+const NUL_CHAR: char = '.'; // blank on a DOS screen, but zeros are easier to count as dots
+const HEX_X: usize = 12;
+const ASCII_X: usize = 62;
+const ADDR_LEN: usize = 9; // seg:off the columns above are laid out for
+const ST_CURSOR: Style = Style::normal(super::FG_GRAY, super::BG_DARK);
+
+/// How the memory is laid out on a line.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum Mode {
+    #[default]
+    Byte,
+    Word,
+    Dword,
+    Short,
+    Long,
+    Float,
+    Double,
+    Real80,
+    Far,
+}
+
+impl Mode {
+    fn next(self) -> Self {
+        match self {
+            Self::Byte => Self::Word,
+            Self::Word => Self::Dword,
+            Self::Dword => Self::Short,
+            Self::Short => Self::Long,
+            Self::Long => Self::Float,
+            Self::Float => Self::Double,
+            Self::Double => Self::Real80,
+            Self::Real80 => Self::Far,
+            Self::Far => Self::Byte,
+        }
+    }
+
+    /// Number of bytes in a cell.
+    fn size(self) -> usize {
+        match self {
+            Self::Byte => 1,
+            Self::Word | Self::Short => 2,
+            Self::Dword | Self::Long | Self::Float | Self::Far => 4,
+            Self::Double => 8,
+            Self::Real80 => 10,
+        }
+    }
+
+    /// Number of cells on a line.
+    fn cells(self) -> usize {
+        match self {
+            Self::Byte => 16,
+            Self::Word | Self::Short => 8,
+            Self::Dword | Self::Long | Self::Float | Self::Far => 4,
+            Self::Double | Self::Real80 => 2,
+        }
+    }
 
-const BYTES_PER_LINE: usize = 16;
-const NON_ASCII_CHAR: char = '.';
+    /// Number of characters in a cell.
+    fn width(self) -> usize {
+        match self {
+            Self::Byte => 2,
+            Self::Word => 4,
+            Self::Dword => 8,
+            Self::Short => 6,
+            Self::Long => 11,
+            Self::Float => 12,
+            Self::Double => 22,
+            Self::Real80 => REAL80_WIDTH,
+            Self::Far => 9,
+        }
+    }
+
+    fn line_len(self) -> usize {
+        self.size() * self.cells()
+    }
+
+    fn format(self, b: &[u8]) -> String {
+        let w = self.width();
+
+        if b.len() < self.size() {
+            return format!("{:>w$}", "");
+        }
+
+        match self {
+            Self::Byte => format!("{:02X}", b[0]),
+            Self::Word => format!("{:04X}", u16::from_le_bytes([b[0], b[1]])),
+            Self::Dword => format!("{:08X}", u32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Self::Short => format!("{:>w$}", i16::from_le_bytes([b[0], b[1]])),
+            Self::Long => format!("{:>w$}", i32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Self::Float => format!("{:>w$.5e}", f32::from_le_bytes([b[0], b[1], b[2], b[3]])),
+            Self::Double => {
+                let v = f64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+
+                format!("{v:>w$.14e}")
+            }
+            Self::Real80 => format!("{:>w$}", real80(b)),
+            Self::Far => format!(
+                "{:04X}:{:04X}",
+                u16::from_le_bytes([b[2], b[3]]),
+                u16::from_le_bytes([b[0], b[1]])
+            ),
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct Properties {
     pub status: PaneStatus,
-    pub proxy: Rc<Proxy>,
+    pub target: Rc<dyn Target>,
     pub addr: Address,
+    pub on_watch: Callback<Address>,
+    /// Tells where the cursor moved, None once it's gone.
+    pub on_cursor: Callback<Option<Address>>,
 }
 
 impl PartialEq for Properties {
@@ -31,13 +144,221 @@ pub struct Data {
     data: Vec<u8>,
     skip: usize,
     pos: Option<usize>,
+    /// Cell under the cursor.
+    col: usize,
+    mode: Mode,
+    codepage: CodePage,
+    ascii: bool,
+    /// The high nibble of the byte under the cursor is typed in already.
+    nibble: bool,
+    /// Bytes written since the last step.
+    edited: Vec<Address>,
 }
 
 pub enum Message {
     Up,
     Down,
+    Left,
+    Right,
     Enter,
     Escape,
+    SwitchColumn,
+    SwitchMode,
+    SwitchCodePage,
+    Watch,
+    Char(char),
+}
+
+impl Data {
+    fn cursor(&self) -> Option<(usize, Address)> {
+        let index = (self.skip + self.pos?) * self.mode.line_len() + self.col * self.mode.size();
+        let addr = self.addr.with_offset(self.addr.offset() + index as u32);
+
+        Some((index, addr))
+    }
+
+    fn notify_cursor(&self, prev: Option<Address>) {
+        let addr = self.cursor().map(|(_, a)| a);
+
+        if addr != prev {
+            self.props.on_cursor.emit(addr);
+        }
+    }
+
+    fn update_impl(&mut self, message: Message) -> ShouldRender {
+        let prev = (self.skip, self.pos, self.data.len(), self.col, self.ascii);
+        let nibble = self.nibble;
+        self.nibble = false;
+
+        match message {
+            Message::Up if self.pos.is_some_and(|x| x > 0) => {
+                if let Some(pos) = self.pos.as_mut() {
+                    *pos -= 1;
+                }
+            }
+            Message::Up if self.skip > 0 => {
+                self.skip -= 1;
+            }
+            Message::Up => {
+                let limit = bytes_on_screen(&self.frame, self.mode);
+                let start = self.addr.offset().saturating_sub(limit);
+
+                if start < self.addr.offset() {
+                    match self
+                        .props
+                        .target
+                        .read_mem(self.addr.with_offset(start), limit)
+                    {
+                        Ok(mut d) if !d.is_empty() => {
+                            self.skip = (d.len() / self.mode.line_len()).saturating_sub(1);
+                            d.append(&mut self.data);
+                            self.data = d;
+                            self.addr = self.addr.with_offset(start);
+                        }
+                        Ok(_) => (),
+                        Err(e) => self.error = Some(e),
+                    }
+                }
+            }
+            Message::Down if self.pos.is_some_and(|x| x < self.frame.height() - 1) => {
+                if let Some(pos) = self.pos.as_mut() {
+                    *pos += 1;
+                }
+            }
+            Message::Down
+                if (self.data.len() / self.mode.line_len())
+                    .saturating_sub(self.skip)
+                    .saturating_sub(self.pos.unwrap_or(self.frame.height()))
+                    > 0 =>
+            {
+                self.skip += 1;
+            }
+            Message::Down => {
+                let start = self.addr.offset().saturating_add(self.data.len() as u32);
+
+                match self.props.target.read_mem(
+                    self.addr.with_offset(start),
+                    bytes_on_screen(&self.frame, self.mode),
+                ) {
+                    Ok(d) if !d.is_empty() => {
+                        self.skip += 1;
+                        self.data.extend(d)
+                    }
+                    Ok(_) => (),
+                    Err(e) => self.error = Some(e),
+                }
+            }
+            Message::Left if self.pos.is_some() && self.col > 0 => self.col -= 1,
+            Message::Right if self.pos.is_some() && self.col + 1 < self.mode.cells() => {
+                self.col += 1;
+            }
+            Message::SwitchColumn if self.pos.is_some() && self.mode == Mode::Byte => {
+                self.ascii = !self.ascii;
+            }
+            Message::SwitchCodePage => {
+                self.codepage = self.codepage.next();
+
+                return true.into();
+            }
+            Message::SwitchMode => {
+                // the top line stays where it is
+                let offset = self.addr.offset() + (self.skip * self.mode.line_len()) as u32;
+                let mode = self.mode.next();
+
+                match self.props.target.read_mem(
+                    self.addr.with_offset(offset),
+                    bytes_on_screen(&self.frame, mode),
+                ) {
+                    Ok(d) => {
+                        self.addr = self.addr.with_offset(offset);
+                        self.data = d;
+                        self.skip = 0;
+                        self.col = 0;
+                        self.ascii = false;
+                        self.mode = mode;
+                    }
+                    Err(e) => self.error = Some(e),
+                }
+
+                return true.into();
+            }
+            Message::Char(c) if self.pos.is_some() && (self.ascii || c.is_ascii_hexdigit()) => {
+                self.nibble = nibble;
+                self.edit(c);
+
+                return true.into();
+            }
+            Message::Watch => {
+                let offset = self.addr.offset() + (self.skip * self.mode.line_len()) as u32;
+                let addr = self
+                    .cursor()
+                    .map(|(_, a)| a)
+                    .unwrap_or(self.addr.with_offset(offset));
+
+                self.props.on_watch.emit(addr);
+            }
+            Message::Enter if self.pos.is_none() => self.pos = Some(0),
+            Message::Escape => self.pos = None,
+            _ => (),
+        }
+
+        (self.nibble != nibble
+            || (self.skip, self.pos, self.data.len(), self.col, self.ascii) != prev)
+            .into()
+    }
+
+    /// Overwrites the byte under the cursor, the cursor moves on once the byte is complete.
+    fn edit(&mut self, c: char) {
+        let Some((index, addr)) = self.cursor().filter(|_| self.mode == Mode::Byte) else {
+            return;
+        };
+
+        let Some(&old) = self.data.get(index) else {
+            return;
+        };
+
+        let value = if self.ascii {
+            match self.codepage.encode(c) {
+                Some(v) => v,
+                None => return,
+            }
+        } else {
+            match c.to_digit(16) {
+                Some(d) if self.nibble => (old & 0xF0) | d as u8,
+                Some(d) => (old & 0x0F) | (d as u8) << 4,
+                None => return,
+            }
+        };
+
+        if let Err(e) = self.props.target.write_mem(addr, value) {
+            self.error = Some(e);
+            return;
+        }
+
+        self.data[index] = value;
+
+        if !self.edited.contains(&addr) {
+            self.edited.push(addr);
+        }
+
+        if self.ascii || self.nibble {
+            self.nibble = false;
+
+            if self.col + 1 < self.mode.cells() {
+                self.col += 1;
+            } else {
+                // on to the start of the next line, it scrolls in if needed
+                let line = (self.skip, self.pos);
+                self.update_impl(Message::Down);
+
+                if (self.skip, self.pos) != line {
+                    self.col = 0;
+                }
+            }
+        } else {
+            self.nibble = true;
+        }
+    }
 }
 
 impl Component for Data {
@@ -51,12 +372,11 @@ impl Component for Data {
             (Vec::new(), Some(anyhow!("Not attached.")))
         } else {
             match props
-                .proxy
-                .mem
-                .get(addr.segment, addr.offset, bytes_on_screen(&frame))
+                .target
+                .read_mem(addr, bytes_on_screen(&frame, Mode::default()))
             {
                 Ok(c) => (c, None),
-                Err(e) => (Vec::new(), Some(e.into())),
+                Err(e) => (Vec::new(), Some(e)),
             }
         };
 
@@ -68,6 +388,12 @@ impl Component for Data {
             data,
             skip: 0,
             pos: None,
+            col: 0,
+            mode: Mode::default(),
+            codepage: CodePage::default(),
+            ascii: false,
+            nibble: false,
+            edited: Vec::new(),
         }
     }
 
@@ -81,26 +407,45 @@ impl Component for Data {
             return true.into();
         }
 
-        let offset = self.addr.offset + (self.skip * BYTES_PER_LINE) as u32;
+        if props.status.reload {
+            self.edited.clear();
+        }
+
+        let cursor = self.cursor().map(|(_, a)| a);
+
+        // keep the scroll position unless asked to go somewhere else
+        let addr = if self.props.addr != props.addr {
+            props.addr
+        } else {
+            let offset = self.addr.offset() + (self.skip * self.mode.line_len()) as u32;
+
+            self.addr.with_offset(offset)
+        };
 
         match self
             .props
-            .proxy
-            .mem
-            .get(self.addr.segment, offset, bytes_on_screen(&self.frame))
+            .target
+            .read_mem(addr, bytes_on_screen(&self.frame, self.mode))
         {
             Ok(d) => {
-                self.addr.offset = offset;
+                // a step keeps the cursor on the same line, going somewhere else drops it
+                let lines = d.len() / self.mode.line_len();
+                self.pos = self
+                    .pos
+                    .filter(|_| self.props.addr == props.addr && lines > 0)
+                    .map(|pos| pos.min(lines - 1));
+
+                self.addr = addr;
                 self.data = d;
                 self.skip = 0;
-                self.pos = None;
                 self.error = None;
             }
-            Err(e) => self.error = Some(e.into()),
+            Err(e) => self.error = Some(e),
         }
 
         self.props = props;
         self.props.status.reload = false;
+        self.notify_cursor(cursor);
 
         true.into()
     }
@@ -110,76 +455,11 @@ impl Component for Data {
             return false.into();
         }
 
-        let prev = (self.skip, self.pos, self.data.len());
+        let cursor = self.cursor().map(|(_, a)| a);
+        let render = self.update_impl(message);
+        self.notify_cursor(cursor);
 
-        match message {
-            Message::Up if self.pos.is_some_and(|x| x > 0) => {
-                if let Some(pos) = self.pos.as_mut() {
-                    *pos -= 1;
-                }
-            }
-            Message::Up if self.skip > 0 => {
-                self.skip -= 1;
-            }
-            Message::Up => {
-                let limit = bytes_on_screen(&self.frame);
-                let start = self.addr.offset.saturating_sub(limit);
-
-                if start < self.addr.offset {
-                    match self.props.proxy.mem.get(self.addr.segment, start, limit) {
-                        Ok(mut d) if !d.is_empty() => {
-                            self.skip = (d.len() / BYTES_PER_LINE).saturating_sub(1);
-                            d.append(&mut self.data);
-                            self.data = d;
-                            self.addr.offset = start;
-                        }
-                        Ok(_) => (),
-                        Err(e) => self.error = Some(e.into()),
-                    }
-                }
-            }
-            Message::Down if self.pos.is_some_and(|x| x < self.frame.height() - 1) => {
-                if let Some(pos) = self.pos.as_mut() {
-                    *pos += 1;
-                }
-            }
-            Message::Down
-                if (self.data.len() / BYTES_PER_LINE)
-                    .saturating_sub(self.skip)
-                    .saturating_sub(self.pos.unwrap_or(self.frame.height()))
-                    > 0 =>
-            {
-                self.skip += 1;
-            }
-            Message::Down => {
-                let start = self.addr.offset.saturating_add(self.data.len() as u32);
-
-                match self.props.proxy.mem.get(
-                    self.addr.segment,
-                    start,
-                    bytes_on_screen(&self.frame),
-                ) {
-                    Ok(d) if !d.is_empty() => {
-                        self.skip += 1;
-                        self.data.extend(d)
-                    }
-                    Ok(_) => (),
-                    Err(e) => self.error = Some(e.into()),
-                }
-            }
-            Message::Enter => {
-                if self.pos.is_none() {
-                    self.pos = Some(0);
-                }
-            }
-            Message::Escape => {
-                if self.pos.is_some() {
-                    self.pos = None;
-                }
-            }
-        }
-
-        ((self.skip, self.pos, self.data.len()) != prev).into()
+        render
     }
 
     fn bindings(&self, bind: &mut Bindings<Self>) {
@@ -194,6 +474,22 @@ impl Component for Data {
         bind.command("enter", || Message::Enter)
             .with([Key::Char('\n')]);
         bind.command("escape", || Message::Escape).with([Key::Esc]);
+        bind.command("left", || Message::Left).with([Key::Left]);
+        bind.command("right", || Message::Right).with([Key::Right]);
+        bind.command("switch-column", || Message::SwitchColumn)
+            .with([Key::Char('\t')]);
+        bind.command("switch-mode", || Message::SwitchMode)
+            .with([Key::Ctrl('d')]);
+        bind.command("switch-code-page", || Message::SwitchCodePage)
+            .with([Key::Ctrl('e')]);
+        // the byte under the cursor, or the top line without one
+        bind.command("watch", || Message::Watch)
+            .with([Key::Ctrl('w')]);
+        // hex digits, or any character in the text column, overwrite the byte under the cursor
+        bind.add("char", AnyCharacter, |keys: &[Key]| match keys {
+            &[Key::Char(c)] if c != '\n' && c != '\r' => Some(Message::Char(c)),
+            _ => None,
+        });
     }
 
     fn resize(&mut self, frame: Rect) -> ShouldRender {
@@ -215,7 +511,16 @@ impl Component for Data {
         let mut canvas = Canvas::new(self.frame.size);
         canvas.clear(super::ST_NORMAL);
 
-        for (y, bytes) in self.data.chunks(BYTES_PER_LINE).skip(self.skip).enumerate() {
+        let mode = self.mode;
+        // selector:offset is wider than seg:off
+        let pad = self.addr.to_string().len().saturating_sub(ADDR_LEN);
+
+        for (y, bytes) in self
+            .data
+            .chunks(mode.line_len())
+            .skip(self.skip)
+            .enumerate()
+        {
             let mut addr_st = super::ST_CAPTION;
             let mut data_st = super::ST_NORMAL;
 
@@ -229,46 +534,55 @@ impl Component for Data {
                 );
             }
 
-            canvas.draw_str(
-                0,
-                y,
-                addr_st,
-                &format!(
-                    "{:04X}:{:04X}",
-                    self.addr.segment,
-                    self.addr.offset as usize + (y + self.skip) * BYTES_PER_LINE
-                ),
-            );
-            canvas.draw_str(
-                12,
-                y,
-                data_st,
-                &bytes
-                    .iter()
-                    .fold(String::new(), |a, x| format!("{a}{x:02X} ")),
-            );
-            canvas.draw_str(
-                62,
-                y,
-                data_st,
-                &bytes.iter().fold(String::new(), |a, x| {
-                    let c = char::from(*x);
-                    format!(
-                        "{a}{}",
-                        if matches!(*x, 32..=0x7E) {
-                            c
-                        } else {
-                            NON_ASCII_CHAR
-                        }
-                    )
-                }),
-            );
+            let line = self.addr.offset() + ((y + self.skip) * mode.line_len()) as u32;
+            canvas.draw_str(0, y, addr_st, &self.addr.with_offset(line).to_string());
+
+            for (x, cell) in bytes.chunks(mode.size()).enumerate() {
+                let addr = self.addr.with_offset(line + (x * mode.size()) as u32);
+                let mut st = data_st;
+
+                if self.edited.contains(&addr) {
+                    st.foreground = super::ST_ACTIVE.foreground;
+                }
+
+                let text = mode.format(cell);
+                let cursor = self.props.status.focused && self.pos == Some(y) && self.col == x;
+                let x0 = HEX_X + pad + x * (mode.width() + 1);
+
+                canvas.draw_str(x0, y, st, &text);
+
+                if mode != Mode::Byte {
+                    if cursor {
+                        canvas.draw_str(x0, y, ST_CURSOR, &text);
+                    }
+
+                    continue;
+                }
+
+                let b = cell[0];
+                let c = if b == 0 {
+                    NUL_CHAR
+                } else {
+                    self.codepage.decode(b)
+                };
+
+                canvas.draw_str(ASCII_X + pad + x, y, st, &c.to_string());
+
+                if cursor {
+                    if self.ascii {
+                        canvas.draw_str(ASCII_X + pad + x, y, ST_CURSOR, &c.to_string());
+                    } else {
+                        let n = usize::from(self.nibble);
+                        canvas.draw_str(x0 + n, y, ST_CURSOR, &text[n..n + 1]);
+                    }
+                }
+            }
         }
 
         canvas.into()
     }
 }
 
-fn bytes_on_screen(rect: &Rect) -> u32 {
-    (rect.size.height * BYTES_PER_LINE) as u32
+fn bytes_on_screen(rect: &Rect, mode: Mode) -> u32 {
+    (rect.size.height * mode.line_len()) as u32
 }
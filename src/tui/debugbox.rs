@@ -1,4 +1,5 @@
-use anyhow::Result;
+use anyhow::{anyhow, bail, Result};
+use iced_x86::{Formatter, IntelFormatter};
 use std::rc::Rc;
 use zi::{
     components::border::{Border, BorderProperties, BorderStroke},
@@ -6,15 +7,29 @@ use zi::{
 };
 
 use crate::{
-    bus::{Proxy, Regs},
+    breakpoints::{Breakpoints, Condition, Kind, WatchHit},
+    bus::{CpuMode, Regs},
+    target::Target,
     tui::{
+        breakpoints::{Breakpoints as BreakpointsPane, Properties as BreakpointsProperties},
+        calls::{Calls, Properties as CallsProperties},
         code::{Code, Properties as CodeProperties},
         data::{Data, Properties as DataProperties},
-        registers::Registers,
+        descriptors::{Descriptors, Properties as DescriptorsProperties},
+        fpu::{Fpu, Properties as FpuProperties},
+        paging::{Paging, Properties as PagingProperties},
+        prompt::{Prompt as PromptLine, Properties as PromptProperties},
+        registers::{Properties as RegistersProperties, Registers},
+        stack::{Properties as StackProperties, Stack},
         status_bar::{Status, StatusBar},
-        PaneStatus,
+        PaneStatus, Scroll,
+    },
+    x86::{
+        dec::{fetch_after, run_to, step_out, step_over, Trap},
+        desc::Table,
+        expr::{self, Expr, Location},
+        Address,
     },
-    x86::dec::step_over,
 };
 
 const FG_SELECTED: Colour = Colour::rgb(0, 255, 0);
@@ -22,30 +37,99 @@ const BORDER_NORMAL: Style = Style::normal(super::BG_DARK, super::FG_GRAY);
 const BORDER_SELECTED: Style = Style::normal(super::BG_DARK, FG_SELECTED);
 const BORDER_STROKE: BorderStroke = BorderStroke::heavy();
 
-#[derive(Default, PartialEq)]
+#[derive(Clone, Copy, Default, PartialEq)]
 pub enum Pane {
     #[default]
     Code,
     Data,
     Registers,
+    Breakpoints,
+    Stack,
+    Calls,
+    Descriptors,
+    Paging,
+    Fpu,
+}
+
+impl Pane {
+    /// Panes shown one at a time in the slot below the data, by their Alt keys.
+    const AUXILIARY: [(Self, &'static str); 5] = [
+        (Self::Stack, "stack"),
+        (Self::Calls, "calls"),
+        (Self::Descriptors, "descriptors"),
+        (Self::Paging, "paging"),
+        (Self::Fpu, "fpu"),
+    ];
+
+    fn is_auxiliary(self) -> bool {
+        Self::AUXILIARY.iter().any(|(p, _)| *p == self)
+    }
+}
+
+/// What the text typed into the prompt is for.
+#[derive(Clone, Copy)]
+pub enum Prompt {
+    Condition(usize),
+    Count(usize),
+    IntBreakpoint,
+    Watch(Kind),
+    Register(&'static str),
+    Goto(Pane),
 }
 
 pub enum Message {
     Reload,
+    Break,
     ChangePane(Pane),
     Run,
+    BreakIn,
     StepOver,
     StepIn,
+    StepOut,
+    RunTo(Address),
+    SetIp(Address),
+    ToggleBreakpoint(Address),
+    RemoveBreakpoint(usize),
+    EnableBreakpoint(usize, bool),
+    Watch(Address),
+    SetRegister(&'static str, u32),
+    Goto,
+    ShowCode(Address),
+    ShowData(Address),
+    DataCursor(Option<Address>),
+    Scroll(Pane, Scroll),
+    DescriptorTable(Table),
+    OpenPrompt(Prompt),
+    Submit(String),
+    CancelPrompt,
 }
 
 pub struct DebugBox {
     frame: Rect,
     link: ComponentLink<Self>,
     pane: Pane,
+    /// Which of the auxiliary panes the shared slot shows.
+    aux: Pane,
+    /// Where the stack, the calls and the descriptors are scrolled to while hidden.
+    stack_scroll: Scroll,
+    calls_scroll: Scroll,
+    desc_scroll: Scroll,
+    desc_table: Table,
     reload: bool,
-    proxy: Rc<Proxy>,
+    target: Rc<dyn Target>,
     status: Status,
     regs: Regs,
+    mode: CpuMode,
+    /// Code shown somewhere else than at CS:EIP until the next step.
+    code_addr: Option<Address>,
+    data_addr: Address,
+    /// Where the cursor of the data pane is, if it's there.
+    data_cursor: Option<Address>,
+    traps: Vec<Trap>,
+    break_in: bool,
+    breakpoints: Breakpoints,
+    prompt: Option<Prompt>,
+    prompt_error: Option<String>,
 }
 
 impl DebugBox {
@@ -54,8 +138,44 @@ impl DebugBox {
 
         match message {
             Message::Reload => {
-                self.regs = self.proxy.regs.get()?;
-                self.status = Status::Attached;
+                self.load_regs()?;
+                self.status = Status::Attached(None);
+                self.reload = true;
+
+                Ok(true)
+            }
+            Message::Break => {
+                let regs = self.target.regs()?;
+                let mode = self.target.cpu_mode()?;
+                let ip = mode.address(regs.cs, regs.eip);
+                let watch = self.target.watch_hit()?;
+
+                // a stop asked for by the user is never skipped
+                if !std::mem::take(&mut self.break_in) {
+                    match self.traps.iter_mut().find(|t| t.addr() == ip) {
+                        Some(trap) if !trap.reached(&regs) => {
+                            trap.pass(&*self.target)?;
+                            self.target.run()?;
+
+                            return Ok(true);
+                        }
+                        Some(_) => (),
+                        None if !self.breakpoints.hit(&*self.target, &regs, watch.as_ref())? => {
+                            self.target.run()?;
+
+                            return Ok(true);
+                        }
+                        None => (),
+                    }
+                }
+
+                for trap in self.traps.drain(..) {
+                    trap.restore(&*self.target)?;
+                }
+
+                self.regs = regs;
+                self.mode = mode;
+                self.status = Status::Attached(watch.map(|w| self.watch_message(&w)));
                 self.reload = true;
 
                 Ok(true)
@@ -63,58 +183,393 @@ impl DebugBox {
             Message::ChangePane(pane) => {
                 self.pane = pane;
 
+                if pane.is_auxiliary() {
+                    self.aux = pane;
+                }
+
                 Ok(true)
             }
-            Message::Run if self.status == Status::Attached => {
-                self.proxy.cpu.run()?;
+            Message::Run if self.status.is_attached() => {
+                self.target.run()?;
                 self.status = Status::Detached(None);
 
                 Ok(true)
             }
-            Message::StepOver if self.status == Status::Attached => {
-                step_over(&self.proxy, (self.regs.cs, self.regs.eip).into())?;
+            Message::BreakIn if !self.status.is_attached() => {
+                self.target.break_in()?;
+                self.break_in = true;
+
+                Ok(false)
+            }
+            Message::StepOver if self.status.is_attached() => {
+                let trap = step_over(&*self.target, self.ip())?;
+
+                self.run_to_trap(trap)
+            }
+            Message::StepOut if self.status.is_attached() => {
+                let trap = step_out(&*self.target, &self.regs)?;
 
-                self.regs = self.proxy.regs.get()?;
+                self.run_to_trap(trap)
+            }
+            Message::RunTo(addr) if self.status.is_attached() && addr != self.ip() => {
+                let trap = run_to(&*self.target, addr, None)?;
+
+                self.run_to_trap(trap)
+            }
+            Message::SetIp(addr) if self.status.is_attached() => {
+                let Some(segment) = addr.segment() else {
+                    bail!("CS:EIP can't be set to {addr}.");
+                };
+
+                if segment != self.regs.cs {
+                    self.target.set_reg("cs", segment.into())?;
+                }
+
+                self.target.set_reg("eip", addr.offset())?;
+                self.load_regs()?;
+                self.status = Status::Attached(None);
                 self.reload = true;
 
                 Ok(true)
             }
-            Message::StepIn if self.status == Status::Attached => {
-                self.proxy.cpu.step_in()?;
-                self.regs = self.proxy.regs.get()?;
+            Message::StepIn if self.status.is_attached() => {
+                self.target.step_in()?;
+                self.load_regs()?;
+                self.status = Status::Attached(None);
                 self.reload = true;
 
                 Ok(true)
             }
+            Message::ToggleBreakpoint(addr) => {
+                self.breakpoints.toggle(&*self.target, addr)?;
+
+                Ok(true)
+            }
+            Message::RemoveBreakpoint(index) => {
+                self.breakpoints.remove(&*self.target, index)?;
+
+                Ok(true)
+            }
+            Message::EnableBreakpoint(index, enabled) => {
+                self.breakpoints.enable(&*self.target, index, enabled)?;
+
+                Ok(true)
+            }
+            Message::Watch(addr) => {
+                self.prompt = Some(Prompt::Watch(Kind::Write(addr)));
+                self.prompt_error = None;
+
+                Ok(true)
+            }
+            Message::SetRegister(name, value) if self.status.is_attached() => {
+                self.set_register(name, value)?;
+
+                Ok(true)
+            }
+            Message::Goto => {
+                let pane = if self.pane == Pane::Data {
+                    Pane::Data
+                } else {
+                    Pane::Code
+                };
+
+                self.prompt = Some(Prompt::Goto(pane));
+                self.prompt_error = None;
+
+                Ok(true)
+            }
+            Message::ShowCode(addr) => {
+                self.code_addr = Some(addr);
+                self.pane = Pane::Code;
+
+                Ok(true)
+            }
+            Message::ShowData(addr) => {
+                self.data_addr = addr;
+                self.pane = Pane::Data;
+
+                Ok(true)
+            }
+            Message::DataCursor(addr) => {
+                self.data_cursor = addr;
+
+                Ok(true)
+            }
+            Message::Scroll(pane, scroll) => {
+                match pane {
+                    Pane::Stack => self.stack_scroll = scroll,
+                    Pane::Calls => self.calls_scroll = scroll,
+                    _ => self.desc_scroll = scroll,
+                }
+
+                Ok(false)
+            }
+            Message::DescriptorTable(table) => {
+                self.desc_table = table;
+                self.desc_scroll = Scroll::default();
+
+                Ok(true)
+            }
+            Message::OpenPrompt(prompt) => {
+                self.prompt = Some(prompt);
+                self.prompt_error = None;
+
+                Ok(true)
+            }
+            Message::Submit(text) => {
+                if let Some(prompt) = self.prompt {
+                    match self.submit(prompt, text.trim()) {
+                        Ok(()) => self.prompt = None,
+                        Err(e) => self.prompt_error = Some(e.to_string()),
+                    }
+                }
+
+                Ok(true)
+            }
+            Message::CancelPrompt => {
+                self.prompt = None;
+
+                Ok(true)
+            }
             _ => Ok(false),
         }
     }
+
+    /// Waits for the trap the target runs to, there is nothing to wait for after a single step.
+    fn run_to_trap(&mut self, trap: Option<Trap>) -> Result<bool> {
+        if let Some(trap) = trap {
+            self.traps.push(trap);
+            self.status = Status::Detached(None);
+        } else {
+            self.load_regs()?;
+            self.status = Status::Attached(None);
+            self.reload = true;
+        }
+
+        Ok(true)
+    }
+
+    fn submit(&mut self, prompt: Prompt, text: &str) -> Result<()> {
+        match prompt {
+            Prompt::Condition(index) => {
+                let condition = if text.is_empty() {
+                    None
+                } else {
+                    Some(Condition::parse(text)?)
+                };
+
+                self.breakpoints.set_condition(index, condition);
+            }
+            Prompt::Count(index) => {
+                let count = if text.is_empty() {
+                    None
+                } else {
+                    Some(
+                        text.parse()
+                            .map_err(|_| anyhow!("Expected a decimal number."))?,
+                    )
+                };
+
+                self.breakpoints.set_count(index, count);
+            }
+            Prompt::IntBreakpoint => {
+                self.breakpoints
+                    .add(&*self.target, Kind::parse_int(text)?, false)?;
+            }
+            Prompt::Watch(_) => {
+                self.breakpoints.add(
+                    &*self.target,
+                    Kind::parse_watch(text, &self.regs, &*self.target)?,
+                    false,
+                )?;
+            }
+            Prompt::Register(name) => {
+                let value = Expr::parse(text)?.eval(&self.regs, &*self.target)?;
+
+                // segments and the lower words of the general registers
+                if name.len() == 2 && value > 0xFFFF {
+                    bail!("Value {value:X} is too large for {}.", name.to_uppercase());
+                }
+
+                self.set_register(name, value)?;
+            }
+            Prompt::Goto(pane) => {
+                let location = Location::parse(text)?;
+
+                if pane == Pane::Data {
+                    self.data_addr = location.eval(&self.regs, &*self.target, self.regs.ds)?;
+                } else {
+                    let addr = location.eval(&self.regs, &*self.target, self.regs.cs)?;
+                    self.code_addr = Some(addr);
+                }
+
+                self.pane = pane;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn load_regs(&mut self) -> Result<()> {
+        self.regs = self.target.regs()?;
+        self.mode = self.target.cpu_mode()?;
+        // the stack and the calls start over from the top, shown or not
+        self.stack_scroll = Scroll::default();
+        self.calls_scroll = Scroll::default();
+
+        Ok(())
+    }
+
+    fn ip(&self) -> Address {
+        self.mode.address(self.regs.cs, self.regs.eip)
+    }
+
+    /// Writes the register and reloads all of them, the code pane follows a new CS:EIP.
+    fn set_register(&mut self, name: &str, value: u32) -> Result<()> {
+        // the target knows whole registers only
+        let full = expr::full_register(name);
+        let value = if full != name {
+            let mut regs = self.regs;
+            expr::set_register(&mut regs, name, value);
+            expr::register(&regs, full)
+        } else {
+            value
+        };
+
+        self.target.set_reg(full, value)?;
+        self.load_regs()?;
+        self.status = Status::Attached(None);
+        self.reload = true;
+
+        Ok(())
+    }
+
+    fn watch_message(&self, hit: &WatchHit) -> String {
+        let mut text = format!(
+            "{} changed {:02X} → {:02X} by {}",
+            hit.kind, hit.old, hit.new, hit.writer
+        );
+
+        if let Some((ins, _)) = fetch_after(&*self.target, hit.writer, 1)
+            .ok()
+            .and_then(|c| c.into_iter().next())
+        {
+            let mut fmt = IntelFormatter::new();
+            fmt.options_mut().set_space_after_operand_separator(true);
+            text.push(' ');
+            fmt.format(&ins, &mut text);
+        }
+
+        text
+    }
+
+    /// Alt-5 to Alt-9 switch the shared slot, the pane it shows is in brackets.
+    fn aux_title(&self) -> String {
+        let mut title = String::from("Alt-5..9");
+
+        for (pane, name) in Pane::AUXILIARY {
+            title.push(' ');
+
+            if pane == self.aux {
+                title.push_str(&format!("[{name}]"));
+            } else {
+                title.push_str(name);
+            }
+        }
+
+        title
+    }
+
+    fn prompt_properties(&self, prompt: Prompt) -> PromptProperties {
+        let (label, text) = match prompt {
+            Prompt::Condition(index) => (
+                "Break if",
+                self.breakpoints
+                    .get_index(index)
+                    .and_then(|b| b.condition.as_ref())
+                    .map(|c| c.text.clone()),
+            ),
+            Prompt::Count(index) => (
+                "Break after hits",
+                self.breakpoints
+                    .get_index(index)
+                    .and_then(|b| b.count)
+                    .map(|n| n.to_string()),
+            ),
+            Prompt::IntBreakpoint => ("Break on INT (e.g. 21 AH=3D)", None),
+            Prompt::Watch(kind) => ("Break on write to", Some(kind.to_string())),
+            Prompt::Register(name) => (
+                &*name.to_uppercase(),
+                Some(expr::format_number(expr::register(&self.regs, name))),
+            ),
+            Prompt::Goto(Pane::Data) => ("Go to data at", None),
+            Prompt::Goto(_) => ("Go to code at", None),
+        };
+
+        PromptProperties {
+            label: label.to_owned(),
+            text: text.unwrap_or_default(),
+            error: self.prompt_error.clone(),
+            on_submit: self.link.callback(Message::Submit),
+            on_cancel: self.link.callback(|_| Message::CancelPrompt),
+        }
+    }
 }
 
 impl Component for DebugBox {
     type Message = Message;
-    type Properties = Proxy;
+    type Properties = Rc<dyn Target>;
+
+    fn create(target: Self::Properties, frame: Rect, link: ComponentLink<Self>) -> Self {
+        let on_break = link.clone();
 
-    fn create(proxy: Self::Properties, frame: Rect, link: ComponentLink<Self>) -> Self {
         // TODO: need to handle timeout with `monitor_activity` (Call failed: Connection timed out)
-        let (regs, status) = match proxy.regs.get() {
-            Ok(r) => (r, Status::Attached),
-            Err(e) => (Default::default(), Status::Detached(Some(e.to_string()))),
+        let (regs, mode, status) = match target
+            .on_break(Box::new(move || on_break.send(Message::Break)))
+            .and_then(|_| Ok((target.regs()?, target.cpu_mode()?)))
+        {
+            Ok((r, m)) => (r, m, Status::Attached(None)),
+            Err(e) => (
+                Default::default(),
+                Default::default(),
+                Status::Detached(Some(e.to_string())),
+            ),
         };
 
         Self {
             frame,
             link,
             pane: Default::default(),
+            aux: Pane::Stack,
+            stack_scroll: Scroll::default(),
+            calls_scroll: Scroll::default(),
+            desc_scroll: Scroll::default(),
+            desc_table: Table::default(),
             reload: false,
-            proxy: Rc::new(proxy),
+            target,
             status,
             regs,
+            mode,
+            traps: Vec::new(),
+            break_in: false,
+            code_addr: None,
+            data_addr: mode.address(regs.ds, 0),
+            data_cursor: None,
+            breakpoints: Breakpoints::default(),
+            prompt: None,
+            prompt_error: None,
         }
     }
 
     fn update(&mut self, message: Self::Message) -> ShouldRender {
-        match self.update_impl(message) {
+        let result = self.update_impl(message);
+
+        // the code pane follows the CPU again
+        if self.reload {
+            self.code_addr = None;
+        }
+
+        match result {
             Ok(v) => v,
             Err(e) => {
                 self.status = Status::Detached(Some(e.to_string()));
@@ -134,10 +589,17 @@ impl Component for DebugBox {
         bind.command("reload", || Message::Reload)
             .with([Key::Ctrl('r')]);
         bind.command("run", || Message::Run).with([Key::F(5)]);
+        bind.command("break", || Message::BreakIn)
+            .with([Key::F(12)]);
         bind.command("step-over", || Message::StepOver)
             .with([Key::F(10)]);
         bind.command("step-in", || Message::StepIn)
             .with([Key::F(11)]);
+        // zi-term drops modifiers of function keys, Shift-F11 comes as a plain F11
+        bind.command("step-out", || Message::StepOut)
+            .with([Key::Ctrl('o')]);
+        bind.command("goto", || Message::Goto)
+            .with([Key::Ctrl('g')]);
 
         bind.command("code-pane", || Message::ChangePane(Pane::Code))
             .with([Key::Alt('1')]);
@@ -145,6 +607,22 @@ impl Component for DebugBox {
             .with([Key::Alt('2')]);
         bind.command("registers-pane", || Message::ChangePane(Pane::Registers))
             .with([Key::Alt('3')]);
+        bind.command("breakpoints-pane", || {
+            Message::ChangePane(Pane::Breakpoints)
+        })
+        .with([Key::Alt('4')]);
+        bind.command("stack-pane", || Message::ChangePane(Pane::Stack))
+            .with([Key::Alt('5')]);
+        bind.command("calls-pane", || Message::ChangePane(Pane::Calls))
+            .with([Key::Alt('6')]);
+        bind.command("descriptors-pane", || {
+            Message::ChangePane(Pane::Descriptors)
+        })
+        .with([Key::Alt('7')]);
+        bind.command("paging-pane", || Message::ChangePane(Pane::Paging))
+            .with([Key::Alt('8')]);
+        bind.command("fpu-pane", || Message::ChangePane(Pane::Fpu))
+            .with([Key::Alt('9')]);
 
         bind.command("exit", |this: &Self| this.link.exit())
             .with([Key::Ctrl('c')]);
@@ -158,28 +636,188 @@ impl Component for DebugBox {
 
     fn view(&self) -> Layout {
         const REGISTERS_WIDTH: usize = 50;
+        const REGISTERS_HEIGHT: usize = 16;
+        const PAGING_HEIGHT: usize = 7;
+        const FPU_HEIGHT: usize = 10;
 
         let code = CodeProperties {
             status: PaneStatus {
-                attached: self.status == Status::Attached,
-                focused: self.pane == Pane::Code,
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Code && self.prompt.is_none(),
                 reload: self.reload,
             },
-            proxy: self.proxy.clone(),
-            addr: (self.regs.cs, self.regs.eip).into(),
+            target: self.target.clone(),
+            addr: self.code_addr.unwrap_or(self.ip()),
+            ip: self.ip(),
+            breakpoints: self.breakpoints.clone(),
+            on_toggle_breakpoint: self.link.callback(Message::ToggleBreakpoint),
+            on_run_to: self.link.callback(Message::RunTo),
+            on_set_ip: self.link.callback(Message::SetIp),
         };
 
         let data = DataProperties {
             status: PaneStatus {
-                attached: self.status == Status::Attached,
-                focused: self.pane == Pane::Data,
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Data && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+            addr: self.data_addr,
+            on_watch: self.link.callback(Message::Watch),
+            on_cursor: self.link.callback(Message::DataCursor),
+        };
+
+        let regs = RegistersProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Registers && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+            regs: self.regs,
+            mode: self.mode,
+            on_edit: self
+                .link
+                .callback(|name| Message::OpenPrompt(Prompt::Register(name))),
+            on_set: self
+                .link
+                .callback(|(name, value)| Message::SetRegister(name, value)),
+        };
+
+        let stack = StackProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Stack && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+            regs: self.regs,
+            scroll: self.stack_scroll,
+            on_scroll: self
+                .link
+                .callback(|scroll| Message::Scroll(Pane::Stack, scroll)),
+        };
+
+        let calls = CallsProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Calls && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+            regs: self.regs,
+            on_select: self.link.callback(Message::ShowCode),
+            scroll: self.calls_scroll,
+            on_scroll: self
+                .link
+                .callback(|scroll| Message::Scroll(Pane::Calls, scroll)),
+        };
+
+        let descriptors = DescriptorsProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Descriptors && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+            on_code: self.link.callback(Message::ShowCode),
+            on_data: self.link.callback(Message::ShowData),
+            table: self.desc_table,
+            on_table: self.link.callback(Message::DescriptorTable),
+            scroll: self.desc_scroll,
+            on_scroll: self
+                .link
+                .callback(|scroll| Message::Scroll(Pane::Descriptors, scroll)),
+        };
+
+        let paging = PagingProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Paging && self.prompt.is_none(),
                 reload: self.reload,
             },
-            proxy: self.proxy.clone(),
-            addr: (self.regs.ds, 0).into(),
+            target: self.target.clone(),
+            addr: self.data_cursor.unwrap_or(self.data_addr),
+            on_data: self.link.callback(Message::ShowData),
         };
 
-        let regs = self.regs;
+        let fpu = FpuProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Fpu && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            target: self.target.clone(),
+        };
+
+        let breakpoints = BreakpointsProperties {
+            status: PaneStatus {
+                attached: self.status.is_attached(),
+                focused: self.pane == Pane::Breakpoints && self.prompt.is_none(),
+                reload: self.reload,
+            },
+            breakpoints: self.breakpoints.clone(),
+            on_remove: self.link.callback(Message::RemoveBreakpoint),
+            on_enable: self
+                .link
+                .callback(|(index, enabled)| Message::EnableBreakpoint(index, enabled)),
+            on_condition: self
+                .link
+                .callback(|index| Message::OpenPrompt(Prompt::Condition(index))),
+            on_count: self
+                .link
+                .callback(|index| Message::OpenPrompt(Prompt::Count(index))),
+            on_add_int: self
+                .link
+                .callback(|_| Message::OpenPrompt(Prompt::IntBreakpoint)),
+        };
+
+        let aux_title = self.aux_title();
+        let aux_active = self.pane == self.aux;
+        let aux = match self.aux {
+            Pane::Calls => create_pane(
+                "calls",
+                &aux_title,
+                move || Calls::with(calls.clone()),
+                aux_active,
+            ),
+            Pane::Descriptors => create_pane(
+                "descriptors",
+                &aux_title,
+                move || Descriptors::with(descriptors.clone()),
+                aux_active,
+            ),
+            Pane::Paging => create_pane(
+                "paging",
+                &aux_title,
+                move || Paging::with(paging.clone()),
+                aux_active,
+            ),
+            Pane::Fpu => create_pane(
+                "fpu",
+                &aux_title,
+                move || Fpu::with(fpu.clone()),
+                aux_active,
+            ),
+            _ => create_pane(
+                "stack",
+                &aux_title,
+                move || Stack::with(stack.clone()),
+                aux_active,
+            ),
+        };
+
+        // the lists take a third of the column like the code and the data
+        let aux = match self.aux {
+            Pane::Paging => Item::fixed(PAGING_HEIGHT)(aux),
+            Pane::Fpu => Item::fixed(FPU_HEIGHT)(aux),
+            _ => Item::auto(aux),
+        };
+
+        let status_bar = match self.prompt {
+            Some(prompt) => PromptLine::with_key("prompt", self.prompt_properties(prompt)),
+            None => StatusBar::with(self.status.clone()),
+        };
 
         Layout::column([
             Item::auto(Layout::row([
@@ -196,15 +834,24 @@ impl Component for DebugBox {
                         move || Data::with(data.clone()),
                         self.pane == Pane::Data,
                     )),
+                    aux,
+                ])),
+                Item::fixed(REGISTERS_WIDTH)(Layout::column([
+                    Item::fixed(REGISTERS_HEIGHT)(create_pane(
+                        "regs",
+                        "Alt-3",
+                        move || Registers::with(regs.clone()),
+                        self.pane == Pane::Registers,
+                    )),
+                    Item::auto(create_pane(
+                        "breakpoints",
+                        "Alt-4",
+                        move || BreakpointsPane::with(breakpoints.clone()),
+                        self.pane == Pane::Breakpoints,
+                    )),
                 ])),
-                Item::fixed(REGISTERS_WIDTH)(create_pane(
-                    "regs",
-                    "Alt-3",
-                    move || Registers::with(regs),
-                    self.pane == Pane::Registers,
-                )),
             ])),
-            Item::fixed(1)(StatusBar::with(self.status.clone())),
+            Item::fixed(1)(status_bar),
         ])
     }
 }
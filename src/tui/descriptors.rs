@@ -0,0 +1,347 @@
+use anyhow::{anyhow, Error, Result};
+use std::rc::Rc;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+    Callback,
+};
+
+use crate::{
+    target::Target,
+    tui::{PaneStatus, Scroll},
+    x86::{
+        desc::{decode_vectors, Descriptor, Kind, Table},
+        Address,
+    },
+};
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+    /// Shows the code of a code segment or at the target of a gate.
+    pub on_code: Callback<Address>,
+    /// Shows the memory of a data or system segment.
+    pub on_data: Callback<Address>,
+    pub table: Table,
+    /// Asks for the next table, Tab cycles through them.
+    pub on_table: Callback<Table>,
+    pub scroll: Scroll,
+    pub on_scroll: Callback<Scroll>,
+}
+
+impl PartialEq for Properties {
+    fn eq(&self, other: &Properties) -> bool {
+        self.status == other.status && self.table == other.table
+    }
+}
+
+enum Entry {
+    /// Interrupt vector of the real mode IDT.
+    Vector(Address),
+    Descriptor(Descriptor),
+}
+
+/// Entries of the GDT, the LDT or the IDT, the first row tells which one.
+pub struct Descriptors {
+    props: Properties,
+    frame: Rect,
+    error: Option<Error>,
+    base: u32,
+    entries: Vec<Entry>,
+    skip: usize,
+    pos: usize,
+}
+
+pub enum Message {
+    Up,
+    Down,
+    Select,
+    SwitchTable,
+}
+
+impl Descriptors {
+    fn load(&mut self) -> Result<()> {
+        let target = &*self.props.target;
+        let pmode = target.cpu_mode()?.pmode;
+        let (base, data) = target.descriptor_table(self.props.table)?;
+
+        self.base = base;
+        self.entries = if self.props.table == Table::Idt && !pmode {
+            decode_vectors(&data)
+                .into_iter()
+                .map(Entry::Vector)
+                .collect()
+        } else {
+            Descriptor::decode_table(&data)
+                .into_iter()
+                .map(Entry::Descriptor)
+                .collect()
+        };
+
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        self.error = if !self.props.status.attached {
+            Some(anyhow!("Not attached."))
+        } else {
+            self.load().err()
+        };
+
+        if self.error.is_some() {
+            self.entries.clear();
+        }
+
+        if self.skip + self.pos >= self.entries.len() {
+            self.skip = 0;
+            self.pos = 0;
+        }
+    }
+
+    fn select(&self, index: usize) {
+        let selector = self.props.table.selector(index);
+
+        match self.entries.get(index) {
+            Some(Entry::Vector(addr)) => self.props.on_code.emit(*addr),
+            Some(Entry::Descriptor(d)) => match d.kind {
+                Kind::Code { .. } => self.props.on_code.emit(Address::Prot(selector, 0)),
+                Kind::Data { .. } | Kind::System { .. } => {
+                    self.props.on_data.emit(Address::Prot(selector, 0))
+                }
+                Kind::Gate {
+                    name: "task",
+                    selector,
+                    ..
+                } => self.props.on_data.emit(Address::Prot(selector, 0)),
+                Kind::Gate {
+                    selector, offset, ..
+                } => self.props.on_code.emit(Address::Prot(selector, offset)),
+                Kind::Null | Kind::Reserved(_) => (),
+            },
+            None => (),
+        }
+    }
+}
+
+impl Component for Descriptors {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let Scroll { skip, pos } = props.scroll;
+        let mut desc = Self {
+            props,
+            frame,
+            error: None,
+            base: 0,
+            entries: Vec::new(),
+            skip,
+            pos,
+        };
+
+        desc.reload();
+        desc
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false.into();
+        }
+
+        let switched = self.props.table != props.table;
+        let reload =
+            self.props.status.attached != props.status.attached || props.status.reload || switched;
+        self.props = props;
+
+        if switched {
+            self.skip = 0;
+            self.pos = 0;
+        }
+
+        if reload {
+            self.reload();
+        }
+
+        true.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        let prev = (self.skip, self.pos);
+        let index = self.skip + self.pos;
+
+        match message {
+            Message::Up if self.pos > 0 => self.pos -= 1,
+            Message::Up if self.skip > 0 => self.skip -= 1,
+            Message::Down if index + 1 < self.entries.len() => {
+                // the first row is the caption
+                if self.pos + 2 < self.frame.height() {
+                    self.pos += 1;
+                } else {
+                    self.skip += 1;
+                }
+            }
+            Message::Select => self.select(index),
+            Message::SwitchTable => self.props.on_table.emit(self.props.table.next()),
+            _ => (),
+        }
+
+        if (self.skip, self.pos) == prev {
+            return false.into();
+        }
+
+        self.props.on_scroll.emit(Scroll {
+            skip: self.skip,
+            pos: self.pos,
+        });
+
+        true.into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("up", || Message::Up).with([Key::Up]);
+        bind.command("down", || Message::Down).with([Key::Down]);
+        bind.command("select", || Message::Select)
+            .with([Key::Char('\n')]);
+        bind.command("switch-table", || Message::SwitchTable)
+            .with([Key::Char('\t')]);
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        if let Some(e) = &self.error {
+            return Text::with(
+                TextProperties::new()
+                    .style(super::ST_NORMAL)
+                    .align(TextAlign::Centre)
+                    .content(e.to_string()),
+            );
+        }
+
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        let count = match self.entries.first() {
+            Some(Entry::Vector(_)) => format!("{} vectors", self.entries.len()),
+            _ => format!("{} entries", self.entries.len()),
+        };
+
+        canvas.draw_str(
+            0,
+            0,
+            super::ST_CAPTION,
+            &format!("{} at {:08X}, {count} (Tab)", self.props.table, self.base),
+        );
+
+        for (y, (i, entry)) in self
+            .entries
+            .iter()
+            .enumerate()
+            .skip(self.skip)
+            .take(self.frame.size.height.saturating_sub(1))
+            .enumerate()
+        {
+            let (mut num_st, mut st) = (super::ST_CAPTION, super::ST_NORMAL);
+
+            if let Entry::Descriptor(d) = entry {
+                if !d.present {
+                    st = super::ST_CAPTION;
+                }
+            }
+
+            if self.props.status.focused && self.pos == y {
+                num_st.background = super::ST_SELECTED.background;
+                st.background = super::ST_SELECTED.background;
+
+                canvas.clear_region(
+                    Rect::new(Position::new(0, y + 1), Size::new(self.frame.size.width, 1)),
+                    st,
+                );
+            }
+
+            let num = match entry {
+                Entry::Vector(_) => format!("{i:02X}"),
+                Entry::Descriptor(_) => format!("{:04X}", self.props.table.selector(i)),
+            };
+
+            canvas.draw_str(0, y + 1, num_st, &num);
+            canvas.draw_str(num.len() + 1, y + 1, st, &describe(entry));
+        }
+
+        canvas.into()
+    }
+}
+
+/// Type, attributes, base and limit (or where a gate leads), DPL and the present bit.
+fn describe(entry: &Entry) -> String {
+    let d = match entry {
+        Entry::Vector(addr) => return addr.to_string(),
+        Entry::Descriptor(d) => d,
+    };
+
+    let bits = |big| if big { 32 } else { 16 };
+    let flag = |set, c| if set { c } else { '-' };
+
+    let text = match d.kind {
+        Kind::Null => return "null".to_owned(),
+        Kind::Reserved(typ) => format!("type {typ:X}"),
+        Kind::Code {
+            base,
+            limit,
+            big,
+            conforming,
+            readable,
+        } => format!(
+            "code{:<2} x{}{} {base:08X} {limit:08X}",
+            bits(big),
+            flag(readable, 'r'),
+            flag(conforming, 'c'),
+        ),
+        Kind::Data {
+            base,
+            limit,
+            big,
+            expand_down,
+            writable,
+        } => format!(
+            "data{:<2} r{}{} {base:08X} {limit:08X}",
+            bits(big),
+            flag(writable, 'w'),
+            flag(expand_down, 'e'),
+        ),
+        Kind::System { name, base, limit } => format!("{name:<6}     {base:08X} {limit:08X}"),
+        Kind::Gate {
+            name,
+            selector,
+            offset,
+            ..
+        } => format!(
+            "{name:<6}     {:<17}",
+            Address::Prot(selector, offset).to_string()
+        ),
+    };
+
+    let mut text = format!("{text} {} {}", d.dpl, flag(d.present, 'P'));
+
+    if let Kind::Gate {
+        name: "call16" | "call32",
+        params,
+        ..
+    } = d.kind
+    {
+        text.push_str(&format!(" {params} params"));
+    }
+
+    text
+}
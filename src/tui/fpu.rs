@@ -0,0 +1,211 @@
+use anyhow::{anyhow, Error, Result};
+use std::rc::Rc;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+};
+
+use crate::{
+    bus::FpuRegs,
+    target::Target,
+    tui::{
+        registers::{print_flag, print_reg},
+        PaneStatus,
+    },
+    x86::fpu::{real80, Tag, REAL80_WIDTH},
+};
+
+const INFO_X: usize = 30; // control words and flags to the right of the stack
+
+// status word bits
+const CONDITION_CODES: &[(&str, u16)] = &[("C0 ", 8), ("C1 ", 9), ("C2 ", 10), ("C3 ", 14)];
+const EXCEPTIONS: &[(&str, u16)] = &[
+    ("IE", 0),
+    ("DE", 1),
+    ("ZE", 2),
+    ("OE", 3),
+    ("UE", 4),
+    ("PE", 5),
+    ("SF", 6),
+    ("ES", 7),
+];
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+}
+
+impl PartialEq for Properties {
+    fn eq(&self, other: &Properties) -> bool {
+        self.status == other.status
+    }
+}
+
+/// ST0..ST7, the control, status and tag words.
+pub struct Fpu {
+    props: Properties,
+    frame: Rect,
+    error: Option<Error>,
+    fpu: FpuRegs,
+    prev: FpuRegs,
+}
+
+impl Fpu {
+    fn load(&mut self) -> Result<()> {
+        let fpu = self.props.target.fpu()?;
+
+        // a step that leaves the FPU alone clears the highlights of the one before
+        self.prev = std::mem::replace(&mut self.fpu, fpu);
+
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        self.error = if !self.props.status.attached {
+            Some(anyhow!("Not attached."))
+        } else {
+            self.load().err()
+        };
+    }
+}
+
+impl Component for Fpu {
+    type Message = ();
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let mut fpu = Self {
+            props,
+            frame,
+            error: None,
+            fpu: FpuRegs::default(),
+            prev: FpuRegs::default(),
+        };
+
+        fpu.reload();
+        fpu.prev = fpu.fpu.clone();
+        fpu
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false.into();
+        }
+
+        let reload = self.props.status.attached != props.status.attached || props.status.reload;
+        self.props = props;
+
+        if reload {
+            self.reload();
+        }
+
+        true.into()
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        if let Some(e) = &self.error {
+            return Text::with(
+                TextProperties::new()
+                    .style(super::ST_NORMAL)
+                    .align(TextAlign::Centre)
+                    .content(e.to_string()),
+            );
+        }
+
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        let (f, p) = (&self.fpu, &self.prev);
+
+        for i in 0..8 {
+            let value = format_st(f.st(i));
+            let tag = f.tag(i);
+
+            // an empty register keeps whatever was there before
+            let style = if tag != p.tag(i) || value != format_st(p.st(i)) {
+                super::ST_ACTIVE
+            } else if tag == Tag::Empty {
+                super::ST_CAPTION
+            } else {
+                super::ST_NORMAL
+            };
+
+            canvas.draw_str(0, i, super::ST_CAPTION, &format!("ST{i}"));
+            canvas.draw_str(4, i, style, &format!("{tag} {value:>REAL80_WIDTH$}"));
+        }
+
+        let words = [("CW", f.cw, p.cw), ("SW", f.sw, p.sw), ("TW", f.tw, p.tw)];
+
+        for (y, (name, value, prev)) in words.into_iter().enumerate() {
+            print_reg(
+                &mut canvas,
+                (INFO_X, y),
+                name,
+                (value.into(), prev.into()),
+                4,
+                false,
+            );
+        }
+
+        let precision = match (f.cw >> 8) & 3 {
+            0 => "24-bit",
+            2 => "53-bit",
+            3 => "64-bit",
+            _ => "?",
+        };
+        let rounding = match (f.cw >> 10) & 3 {
+            0 => "nearest",
+            1 => "down",
+            2 => "up",
+            _ => "zero",
+        };
+
+        canvas.draw_str(INFO_X + 9, 0, super::ST_CAPTION, "PC");
+        canvas.draw_str(INFO_X + 12, 0, super::ST_NORMAL, precision);
+        canvas.draw_str(INFO_X + 19, 0, super::ST_CAPTION, "RC");
+        canvas.draw_str(INFO_X + 22, 0, super::ST_NORMAL, rounding);
+
+        let top = (f.top() as u32, p.top() as u32);
+        print_reg(&mut canvas, (INFO_X + 9, 1), "TOP", top, 1, false);
+
+        let bit = |word: u16, n: u16| word & (1 << n) != 0;
+
+        for (x, (name, n)) in CONDITION_CODES.iter().enumerate() {
+            let flag = (bit(f.sw, *n), bit(p.sw, *n));
+            print_flag(&mut canvas, (INFO_X + x * 6, 3), name, flag, false);
+        }
+
+        for (x, (name, n)) in EXCEPTIONS.iter().enumerate() {
+            let flag = (bit(f.sw, *n), bit(p.sw, *n));
+            print_flag(&mut canvas, (INFO_X + x * 4, 4), name, flag, false);
+        }
+
+        // exceptions the control word masks
+        let masked: Vec<_> = EXCEPTIONS
+            .iter()
+            .take(6)
+            .filter(|(_, n)| bit(f.cw, *n))
+            .map(|(name, _)| format!("{}M", &name[..1]))
+            .collect();
+
+        canvas.draw_str(INFO_X, 5, super::ST_CAPTION, "masked");
+        canvas.draw_str(INFO_X + 7, 5, super::ST_NORMAL, &masked.join(" "));
+
+        canvas.into()
+    }
+}
+
+fn format_st(b: &[u8]) -> String {
+    if b.is_empty() {
+        String::new()
+    } else {
+        real80(b)
+    }
+}
@@ -1,11 +1,22 @@
 use zi::{Colour, Style};
 
+pub mod breakpoints;
+pub mod calls;
 pub mod code;
+pub mod codepage;
 pub mod data;
 pub mod debugbox;
+pub mod descriptors;
+pub mod fpu;
+pub mod paging;
+pub mod prompt;
 pub mod registers;
+pub mod stack;
 pub mod status_bar;
 
+const BP_ENABLED: &str = "●";
+const BP_DISABLED: &str = "○";
+
 const BG_GRAY: Colour = Colour::rgb(33, 34, 44);
 const BG_DARK: Colour = Colour::rgb(14, 20, 25);
 const FG_GRAY: Colour = Colour::rgb(224, 224, 224);
@@ -15,6 +26,17 @@ const ST_SELECTED: Style = Style::normal(BG_GRAY, FG_GRAY);
 const ST_CAPTION: Style = Style::normal(BG_DARK, Colour::rgb(127, 109, 92));
 const ST_CHANGED: Style = Style::normal(BG_DARK, Colour::rgb(170, 170, 255));
 const ST_ACTIVE: Style = Style::normal(BG_DARK, Colour::rgb(255, 0, 127));
+const ST_BREAKPOINT: Style = Style::normal(BG_DARK, Colour::rgb(255, 64, 64));
+
+/// How far a list pane is scrolled and which of the rows shown is selected.
+///
+/// The panes sharing the slot below the data are created anew each time they are
+/// switched to, so they report it and get it back in their properties.
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct Scroll {
+    pub skip: usize,
+    pub pos: usize,
+}
 
 #[derive(Clone, PartialEq, Eq)]
 pub struct PaneStatus {
@@ -0,0 +1,247 @@
+use anyhow::{anyhow, Error, Result};
+use std::rc::Rc;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+    Callback,
+};
+
+use crate::{
+    bus::ControlRegs,
+    target::Target,
+    tui::PaneStatus,
+    x86::{
+        paging::{linear, walk, Entry, Walk},
+        Address,
+    },
+};
+
+// names of the bits shown after CR0 and CR4
+const CR0_BITS: &[(u32, &str)] = &[
+    (0, "PE"),
+    (1, "MP"),
+    (2, "EM"),
+    (3, "TS"),
+    (4, "ET"),
+    (5, "NE"),
+    (16, "WP"),
+    (18, "AM"),
+    (29, "NW"),
+    (30, "CD"),
+    (31, "PG"),
+];
+const CR4_BITS: &[(u32, &str)] = &[
+    (0, "VME"),
+    (1, "PVI"),
+    (2, "TSD"),
+    (3, "DE"),
+    (4, "PSE"),
+    (5, "PAE"),
+    (6, "MCE"),
+    (7, "PGE"),
+];
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+    /// Address under the cursor of the data pane.
+    pub addr: Address,
+    /// Shows the memory at the physical address.
+    pub on_data: Callback<Address>,
+}
+
+impl PartialEq for Properties {
+    fn eq(&self, other: &Properties) -> bool {
+        self.status == other.status && self.addr == other.addr
+    }
+}
+
+/// Control registers and the translation of the data pane address to a physical one.
+pub struct Paging {
+    props: Properties,
+    frame: Rect,
+    error: Option<Error>,
+    cr: ControlRegs,
+    /// None for an address that is physical already.
+    walk: Option<Walk>,
+}
+
+pub enum Message {
+    Select,
+}
+
+impl Paging {
+    fn load(&mut self) -> Result<()> {
+        let target = &*self.props.target;
+
+        self.cr = target.control_regs()?;
+        self.walk = match linear(target, self.props.addr)? {
+            Some(l) => Some(walk(target, &self.cr, l)?),
+            None => None,
+        };
+
+        Ok(())
+    }
+
+    fn reload(&mut self) {
+        self.error = if !self.props.status.attached {
+            Some(anyhow!("Not attached."))
+        } else {
+            self.load().err()
+        };
+    }
+
+    fn physical(&self) -> Option<u32> {
+        match self.walk {
+            Some(w) => w.physical,
+            None => Some(self.props.addr.offset()),
+        }
+    }
+}
+
+impl Component for Paging {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let mut paging = Self {
+            props,
+            frame,
+            error: None,
+            cr: ControlRegs::default(),
+            walk: None,
+        };
+
+        paging.reload();
+        paging
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false.into();
+        }
+
+        let reload = self.props.status.attached != props.status.attached
+            || self.props.addr != props.addr
+            || props.status.reload;
+        self.props = props;
+
+        if reload {
+            self.reload();
+        }
+
+        true.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Message::Select => {
+                if let Some(p) = self.physical() {
+                    self.props.on_data.emit(Address::Physical(p));
+                }
+            }
+        }
+
+        false.into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("select", || Message::Select)
+            .with([Key::Char('\n')]);
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        if let Some(e) = &self.error {
+            return Text::with(
+                TextProperties::new()
+                    .style(super::ST_NORMAL)
+                    .align(TextAlign::Centre)
+                    .content(e.to_string()),
+            );
+        }
+
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        let cr = &self.cr;
+        let rows = [
+            (
+                "CR0",
+                format!("{:08X} {}", cr.cr0, bit_names(cr.cr0, CR0_BITS)),
+            ),
+            ("CR2", format!("{:08X}", cr.cr2)),
+            ("CR3", format!("{:08X}", cr.cr3)),
+            (
+                "CR4",
+                format!("{:08X} {}", cr.cr4, bit_names(cr.cr4, CR4_BITS)),
+            ),
+        ];
+
+        // control registers on the left, the translation on the right
+        for (y, (name, value)) in rows.iter().enumerate() {
+            canvas.draw_str(0, y, super::ST_CAPTION, name);
+            canvas.draw_str(4, y, super::ST_NORMAL, value);
+        }
+
+        let x = 4 + rows.iter().map(|(_, v)| v.len()).max().unwrap_or(0) + 2;
+        let mut y = 0;
+
+        let mut draw = |caption: &str, text: &str| {
+            canvas.draw_str(x, y, super::ST_CAPTION, caption);
+            canvas.draw_str(x + 5, y, super::ST_NORMAL, text);
+            y += 1;
+        };
+
+        draw("", &self.props.addr.to_string());
+
+        if let Some(w) = &self.walk {
+            draw("Lin", &Address::Linear(w.linear).to_string());
+
+            if let Some(pde) = &w.pde {
+                draw("PDE", &describe(pde));
+            }
+
+            if let Some(pte) = &w.pte {
+                draw("PTE", &describe(pte));
+            }
+        }
+
+        match self.physical() {
+            Some(p) => draw("Phys", &format!("{} (Enter)", Address::Physical(p))),
+            None => draw("Phys", "page not present"),
+        }
+
+        canvas.into()
+    }
+}
+
+/// Where the entry is, its value and flags.
+fn describe(entry: &Entry) -> String {
+    format!(
+        "{} {:08X} {}",
+        Address::Physical(entry.addr),
+        entry.value,
+        entry.flags()
+    )
+}
+
+fn bit_names(value: u32, bits: &[(u32, &str)]) -> String {
+    bits.iter()
+        .filter(|(bit, _)| value & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
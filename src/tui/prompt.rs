@@ -0,0 +1,109 @@
+use zi::{prelude::*, AnyCharacter, Callback};
+
+const STYLE: Style = Style::normal(super::BG_GRAY, super::FG_GRAY);
+const ST_LABEL: Style = Style::normal(super::BG_GRAY, Colour::rgb(127, 109, 92));
+const ST_ERROR: Style = Style::normal(super::BG_GRAY, Colour::rgb(255, 64, 64));
+const ST_CURSOR: Style = Style::normal(super::FG_GRAY, super::BG_GRAY);
+
+#[derive(Clone)]
+pub struct Properties {
+    pub label: String,
+    pub text: String,
+    pub error: Option<String>,
+    pub on_submit: Callback<String>,
+    pub on_cancel: Callback<()>,
+}
+
+/// One-line text input shown in place of the status bar.
+pub struct Prompt {
+    props: Properties,
+    frame: Rect,
+    text: String,
+}
+
+pub enum Message {
+    Insert(char),
+    Backspace,
+    Submit,
+    Cancel,
+}
+
+impl Component for Prompt {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        Self {
+            text: props.text.clone(),
+            props,
+            frame,
+        }
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.label != props.label {
+            self.text = props.text.clone();
+        }
+
+        let changed = self.props.label != props.label || self.props.error != props.error;
+        self.props = props;
+
+        changed.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        match message {
+            Message::Insert(c) => self.text.push(c),
+            Message::Backspace => {
+                self.text.pop();
+            }
+            Message::Submit => self.props.on_submit.emit(self.text.clone()),
+            Message::Cancel => self.props.on_cancel.emit(()),
+        }
+
+        true.into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(true);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("submit", || Message::Submit)
+            .with([Key::Char('\n')]);
+        bind.command("cancel", || Message::Cancel).with([Key::Esc]);
+        bind.command("backspace", || Message::Backspace)
+            .with([Key::Backspace]);
+        bind.add("insert", AnyCharacter, |keys: &[Key]| match keys {
+            &[Key::Char(c)] if c != '\n' && c != '\r' && c != '\t' => Some(Message::Insert(c)),
+            _ => None,
+        });
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(STYLE);
+
+        let mut x = 1;
+
+        canvas.draw_str(x, 0, ST_LABEL, &self.props.label);
+        x += self.props.label.chars().count() + 2;
+        canvas.draw_str(x, 0, STYLE, &self.text);
+        x += self.text.chars().count();
+        canvas.draw_str(x, 0, ST_CURSOR, " ");
+
+        if let Some(e) = &self.props.error {
+            canvas.draw_str(x + 2, 0, ST_ERROR, e);
+        }
+
+        canvas.into()
+    }
+}
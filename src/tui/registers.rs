@@ -1,36 +1,243 @@
-use zi::prelude::*;
+use anyhow::Result;
+use std::rc::Rc;
+use zi::{prelude::*, Callback};
 
-use crate::bus::Regs;
+use crate::{
+    bus::{CpuMode, Regs},
+    target::Target,
+    tui::{codepage::CodePage, PaneStatus},
+    x86::expr,
+};
+
+// registers and flags as the cursor moves over them, by rows on the screen
+const FIELDS: &[&[&str]] = &[
+    &["eax", "esi", "cs"],
+    &["ebx", "edi", "ds"],
+    &["ecx", "ebp", "es"],
+    &["edx", "esp", "ss"],
+    &["eip", "fs", "gs"],
+    &["cf", "zf", "sf", "of", "af", "pf", "df", "if", "tf"],
+    &["efl", "iopl", "nt", "vm", "ac", "id"],
+];
+const FLAGS_ROW: usize = 5; // the rows from here on are flags
+const PREVIEW_LEN: u32 = 32; // bytes read at each pointer, enough for a short string
+const PREVIEW_HEX: usize = 8; // bytes shown in hex
+
+/// Width of the general registers.
+#[derive(Clone, Copy, Default, PartialEq)]
+enum View {
+    /// 32 or 16 bits as the code segment is.
+    #[default]
+    Auto,
+    Dword,
+    Word,
+    /// AX, BX, CX and DX are split into halves, the other ones are 16-bit.
+    Byte,
+}
+
+impl View {
+    fn next(self) -> Self {
+        match self {
+            Self::Auto => Self::Dword,
+            Self::Dword => Self::Word,
+            Self::Word => Self::Byte,
+            Self::Byte => Self::Auto,
+        }
+    }
+
+    fn bits(self, big: bool) -> usize {
+        match self {
+            Self::Auto if big => 32,
+            Self::Auto => 16,
+            Self::Dword => 32,
+            Self::Word => 16,
+            Self::Byte => 8,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+    pub regs: Regs,
+    /// The automatic view follows the size of the code segment.
+    pub mode: CpuMode,
+    /// Asks for a new value of the register.
+    pub on_edit: Callback<&'static str>,
+    pub on_set: Callback<(&'static str, u32)>,
+}
 
 pub struct Registers {
-    regs: Regs,
+    props: Properties,
     prev: Regs,
     frame: Rect,
+    row: usize,
+    col: usize,
+    view: View,
+    /// Memory at DS:SI, ES:DI and the other pointers string routines work with.
+    previews: Vec<(String, Result<Vec<u8>>)>,
+}
+
+pub enum Message {
+    Up,
+    Down,
+    Left,
+    Right,
+    Edit,
+    Toggle,
+    SwitchView,
+}
+
+impl Registers {
+    fn selected(&self) -> Option<&'static str> {
+        if self.props.status.focused {
+            Some(FIELDS[self.row][self.col])
+        } else {
+            None
+        }
+    }
+
+    fn bits(&self) -> usize {
+        self.view.bits(self.props.mode.big)
+    }
+
+    fn load_previews(&mut self) {
+        self.previews.clear();
+
+        if !self.props.status.attached {
+            return;
+        }
+
+        let (r, mode) = (&self.props.regs, self.props.mode);
+        let pointers = [
+            ("DS", r.ds, "SI", r.esi, mode.big),
+            ("ES", r.es, "DI", r.edi, mode.big),
+            ("SS", r.ss, "SP", r.esp, mode.stack_big),
+            ("DS", r.ds, "DX", r.edx, mode.big),
+            ("CS", r.cs, "IP", r.eip, mode.big),
+        ];
+
+        for (segment, value, name, offset, big) in pointers {
+            let (name, offset) = if big {
+                (format!("{segment}:E{name}"), offset)
+            } else {
+                (format!("{segment}:{name}"), offset & 0xFFFF)
+            };
+
+            let data = self
+                .props
+                .target
+                .read_mem(mode.address(value, offset), PREVIEW_LEN);
+
+            self.previews.push((name, data));
+        }
+    }
+
+    /// Name of the field as it's shown, `ax` instead of `eax` in a narrower view.
+    fn shown_name(&self, name: &'static str) -> &'static str {
+        if self.bits() < 32 && name.len() == 3 && name.starts_with('e') && name != "efl" {
+            &name[1..]
+        } else {
+            name
+        }
+    }
 }
 
 impl Component for Registers {
-    type Message = ();
-    type Properties = Regs;
+    type Message = Message;
+    type Properties = Properties;
 
-    fn create(regs: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
-        Self {
-            regs,
-            prev: regs,
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let mut regs = Self {
+            prev: props.regs,
+            props,
             frame,
-        }
+            row: 0,
+            col: 0,
+            view: View::default(),
+            previews: Vec::new(),
+        };
+
+        regs.load_previews();
+        regs
     }
 
-    fn change(&mut self, regs: Self::Properties) -> ShouldRender {
-        if self.regs == regs {
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props.regs == props.regs
+            && self.props.status == props.status
+            && self.props.mode == props.mode
+        {
             return false.into();
         }
 
-        self.prev = self.regs;
-        self.regs = regs;
+        let reload = self.props.regs != props.regs
+            || self.props.status.attached != props.status.attached
+            || props.status.reload;
+
+        if self.props.regs != props.regs {
+            self.prev = self.props.regs;
+        }
+
+        self.props = props;
+
+        if reload {
+            self.load_previews();
+        }
 
         true.into()
     }
 
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        let prev = (self.row, self.col);
+
+        match message {
+            Message::Up if self.row > 0 => self.row -= 1,
+            Message::Down if self.row + 1 < FIELDS.len() => self.row += 1,
+            Message::Left if self.col > 0 => self.col -= 1,
+            Message::Right if self.col + 1 < FIELDS[self.row].len() => self.col += 1,
+            Message::Edit | Message::Toggle => {
+                let name = FIELDS[self.row][self.col];
+
+                if self.row >= FLAGS_ROW && !matches!(name, "efl" | "iopl") {
+                    let value = expr::register(&self.props.regs, name);
+                    self.props.on_set.emit((name, value ^ 1));
+                } else if matches!(message, Message::Edit) {
+                    self.props.on_edit.emit(self.shown_name(name));
+                }
+            }
+            Message::SwitchView => {
+                self.view = self.view.next();
+
+                return true.into();
+            }
+            _ => (),
+        }
+
+        self.col = self.col.min(FIELDS[self.row].len() - 1);
+
+        ((self.row, self.col) != prev).into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("up", || Message::Up).with([Key::Up]);
+        bind.command("down", || Message::Down).with([Key::Down]);
+        bind.command("left", || Message::Left).with([Key::Left]);
+        bind.command("right", || Message::Right).with([Key::Right]);
+        bind.command("edit", || Message::Edit)
+            .with([Key::Char('\n')]);
+        bind.command("toggle", || Message::Toggle)
+            .with([Key::Char(' ')]);
+        bind.command("switch-view", || Message::SwitchView)
+            .with([Key::Char('\t')]);
+    }
+
     fn resize(&mut self, frame: Rect) -> ShouldRender {
         self.frame = frame;
 
@@ -38,26 +245,29 @@ impl Component for Registers {
     }
 
     fn view(&self) -> Layout {
-        let (r, p) = (self.regs, self.prev);
+        let (r, p) = (self.props.regs, self.prev);
+        let selected = self.selected();
+
+        let bits = self.bits();
 
         let regs = [
             [
-                ("EAX", r.eax, p.eax),
-                ("EBX", r.ebx, p.ebx),
-                ("ECX", r.ecx, p.ecx),
-                ("EDX", r.edx, p.edx),
+                ("eax", r.eax, p.eax),
+                ("ebx", r.ebx, p.ebx),
+                ("ecx", r.ecx, p.ecx),
+                ("edx", r.edx, p.edx),
             ],
             [
-                ("ESI", r.esi, p.esi),
-                ("EDI", r.edi, p.edi),
-                ("EBP", r.ebp, p.ebp),
-                ("ESP", r.esp, p.esp),
+                ("esi", r.esi, p.esi),
+                ("edi", r.edi, p.edi),
+                ("ebp", r.ebp, p.ebp),
+                ("esp", r.esp, p.esp),
             ],
             [
-                ("CS", r.cs.into(), p.cs.into()),
-                ("DS", r.ds.into(), p.ds.into()),
-                ("ES", r.es.into(), p.es.into()),
-                ("SS", r.ss.into(), p.ss.into()),
+                ("cs", r.cs.into(), p.cs.into()),
+                ("ds", r.ds.into(), p.ds.into()),
+                ("es", r.es.into(), p.es.into()),
+                ("ss", r.ss.into(), p.ss.into()),
             ],
         ];
 
@@ -68,22 +278,51 @@ impl Component for Registers {
 
         for (x, col) in regs.iter().enumerate() {
             for (y, (name, value, prev)) in col.iter().enumerate() {
-                print_reg(&mut canvas, x * col_width, y, name, *value, *prev);
+                print_field(
+                    &mut canvas,
+                    (x * col_width, y),
+                    name,
+                    (*value, *prev),
+                    bits,
+                    is_selected(selected, name),
+                );
             }
         }
 
-        let y = regs[0].len() + 1;
-        print_reg(&mut canvas, 0, y, "EIP", r.eip, p.eip);
-        print_reg(&mut canvas, col_width, y, "FS", r.fs.into(), p.fs.into());
-        print_reg(
-            &mut canvas,
-            col_width * 2,
+        let y = regs[0].len();
+
+        let view = match self.view {
+            View::Auto => format!("{bits}-bit auto (Tab)"),
+            _ => format!("{bits}-bit (Tab)"),
+        };
+
+        canvas.draw_str(
+            self.frame.size.width.saturating_sub(view.len()),
             y,
-            "GS",
-            r.gs.into(),
-            p.gs.into(),
+            super::ST_CAPTION,
+            &view,
         );
 
+        let y = y + 1;
+
+        for (x, (name, value, prev)) in [
+            ("eip", r.eip, p.eip),
+            ("fs", r.fs.into(), p.fs.into()),
+            ("gs", r.gs.into(), p.gs.into()),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            print_field(
+                &mut canvas,
+                (x * col_width, y),
+                name,
+                (value, prev),
+                bits,
+                is_selected(selected, name),
+            );
+        }
+
         let regs = [
             ("C", r.cf, p.cf),
             ("Z", r.zf, p.zf),
@@ -99,36 +338,197 @@ impl Component for Registers {
         let y = y + 2;
 
         for (x, (name, value, prev)) in regs.into_iter().enumerate() {
-            let x = x * 4;
-
-            canvas.draw_str(x, y, super::ST_CAPTION, name);
-            canvas.draw_str(
-                x + 1,
-                y,
-                if value != prev {
-                    super::ST_ACTIVE
-                } else if value {
-                    super::ST_CHANGED
-                } else {
-                    super::ST_NORMAL
-                },
-                if value { "1" } else { "0" },
-            );
+            let selected = is_selected(selected, &format!("{name}f"));
+            print_flag(&mut canvas, (x * 4, y), name, (value, prev), selected);
+        }
+
+        let y = y + 1;
+
+        print_reg(
+            &mut canvas,
+            (0, y),
+            "EFL",
+            (r.eflags(), p.eflags()),
+            8,
+            is_selected(selected, "efl"),
+        );
+
+        let selected_iopl = is_selected(selected, "iopl");
+        let style = if r._iopl != p._iopl {
+            super::ST_ACTIVE
+        } else {
+            super::ST_NORMAL
+        };
+
+        canvas.draw_str(16, y, select(super::ST_CAPTION, selected_iopl), "IOPL");
+        canvas.draw_str(20, y, select(style, selected_iopl), &r._iopl.to_string());
+
+        let regs = [
+            ("NT", r._nt, p._nt),
+            ("VM", r._vm, p._vm),
+            ("AC", r._ac, p._ac),
+            ("ID", r._id, p._id),
+        ];
+
+        for (x, (name, value, prev)) in regs.into_iter().enumerate() {
+            let selected = is_selected(selected, name);
+            print_flag(&mut canvas, (24 + x * 4, y), name, (value, prev), selected);
+        }
+
+        // segments hold paragraphs even though the CPU is in protected mode
+        if r._vm {
+            canvas.draw_str(41, y, super::ST_ACTIVE, "V86");
+        }
+
+        for (i, (name, data)) in self.previews.iter().enumerate() {
+            let y = y + 1 + i;
+
+            canvas.draw_str(0, y, super::ST_CAPTION, name);
+
+            match data {
+                Ok(data) => {
+                    let hex: Vec<_> = data
+                        .iter()
+                        .take(PREVIEW_HEX)
+                        .map(|b| format!("{b:02X}"))
+                        .collect();
+
+                    canvas.draw_str(7, y, super::ST_NORMAL, &hex.join(" "));
+                    canvas.draw_str(7 + PREVIEW_HEX * 3, y, super::ST_CHANGED, &preview(data));
+                }
+                Err(e) => {
+                    canvas.draw_str(7, y, super::ST_CAPTION, &e.to_string());
+                }
+            }
         }
 
         canvas.into()
     }
 }
 
-fn print_reg(canvas: &mut Canvas, x: usize, y: usize, name: &str, value: u32, prev: u32) {
+/// A DOS string if the bytes look like one, with its terminator as in `db "text",0` or
+/// `db "text$"`, the first bytes as CP437 text otherwise.
+fn preview(data: &[u8]) -> String {
+    let cp = CodePage::Cp437;
+    let text = |bytes: &[u8]| bytes.iter().map(|b| cp.decode(*b)).collect::<String>();
+
+    let end = data.iter().position(|b| *b == 0 || *b == b'$');
+    let printable = |b: &u8| (0x20..0x7F).contains(b) || *b >= 0x80 || b"\t\r\n".contains(b);
+
+    match end {
+        Some(end) if end > 0 && data[..end].iter().all(printable) => {
+            if data[end] == 0 {
+                format!("\"{}\",0", text(&data[..end]))
+            } else {
+                format!("\"{}$\"", text(&data[..end]))
+            }
+        }
+        _ => data
+            .iter()
+            .take(PREVIEW_HEX)
+            .map(|b| if *b == 0 { '.' } else { cp.decode(*b) })
+            .collect(),
+    }
+}
+
+fn is_selected(selected: Option<&str>, name: &str) -> bool {
+    selected.is_some_and(|s| s.eq_ignore_ascii_case(name))
+}
+
+fn select(style: Style, selected: bool) -> Style {
+    if selected {
+        Style {
+            background: super::ST_SELECTED.background,
+            ..style
+        }
+    } else {
+        style
+    }
+}
+
+pub fn print_flag(
+    canvas: &mut Canvas,
+    (x, y): (usize, usize),
+    name: &str,
+    (value, prev): (bool, bool),
+    selected: bool,
+) {
+    let style = if value != prev {
+        super::ST_ACTIVE
+    } else if value {
+        super::ST_CHANGED
+    } else {
+        super::ST_NORMAL
+    };
+
+    canvas.draw_str(x, y, select(super::ST_CAPTION, selected), name);
+    canvas.draw_str(
+        x + name.len(),
+        y,
+        select(style, selected),
+        if value { "1" } else { "0" },
+    );
+}
+
+/// Prints a register the way the view shows it: whole, its lower word or split into bytes.
+fn print_field(
+    canvas: &mut Canvas,
+    (x, y): (usize, usize),
+    name: &str,
+    (value, prev): (u32, u32),
+    bits: usize,
+    selected: bool,
+) {
+    let upper = name.to_uppercase();
+
+    if name.len() == 2 {
+        // a segment register
+        print_reg(canvas, (x, y), &upper, (value, prev), 4, selected);
+    } else if bits == 32 {
+        print_reg(canvas, (x, y), &upper, (value, prev), 8, selected);
+    } else if bits == 8 && name.ends_with('x') {
+        let half = |n: u32, shift: u32| (n >> shift) & 0xFF;
+
+        print_reg(
+            canvas,
+            (x, y),
+            &format!("{}H", &upper[1..2]),
+            (half(value, 8), half(prev, 8)),
+            2,
+            selected,
+        );
+        print_reg(
+            canvas,
+            (x + 6, y),
+            &format!("{}L", &upper[1..2]),
+            (half(value, 0), half(prev, 0)),
+            2,
+            selected,
+        );
+    } else {
+        print_reg(
+            canvas,
+            (x, y),
+            &upper[1..],
+            (value & 0xFFFF, prev & 0xFFFF),
+            4,
+            selected,
+        );
+    }
+}
+
+pub fn print_reg(
+    canvas: &mut Canvas,
+    (x, y): (usize, usize),
+    name: &str,
+    (value, prev): (u32, u32),
+    digits: usize,
+    selected: bool,
+) {
     let mut x = x;
 
     if value != prev {
-        let (val, pre) = &if name.len() == 2 {
-            (format!("{value:04X}"), format!("{prev:04X}"))
-        } else {
-            (format!("{value:08X}"), format!("{prev:08X}"))
-        };
+        let (val, pre) = &(format!("{value:0digits$X}"), format!("{prev:0digits$X}"));
 
         let i = val
             .chars()
@@ -136,25 +536,21 @@ fn print_reg(canvas: &mut Canvas, x: usize, y: usize, name: &str, value: u32, pr
             .enumerate()
             .find_map(|(i, (v, p))| if v != p { Some(i) } else { None });
 
-        canvas.draw_str(x, y, super::ST_CAPTION, name);
+        canvas.draw_str(x, y, select(super::ST_CAPTION, selected), name);
         x += name.len() + 1;
 
         if let Some(i) = i {
-            canvas.draw_str(x, y, super::ST_CHANGED, &val[..i]);
+            canvas.draw_str(x, y, select(super::ST_CHANGED, selected), &val[..i]);
             x += i;
-            canvas.draw_str(x, y, super::ST_ACTIVE, &val[i..]);
+            canvas.draw_str(x, y, select(super::ST_ACTIVE, selected), &val[i..]);
         } else {
-            canvas.draw_str(x, y, super::ST_NORMAL, val);
+            canvas.draw_str(x, y, select(super::ST_NORMAL, selected), val);
         }
     } else {
-        let val = &if name.len() == 2 {
-            format!("{value:04X}")
-        } else {
-            format!("{value:08X}")
-        };
+        let val = &format!("{value:0digits$X}");
 
-        canvas.draw_str(x, y, super::ST_CAPTION, name);
+        canvas.draw_str(x, y, select(super::ST_CAPTION, selected), name);
         x += name.len() + 1;
-        canvas.draw_str(x, y, super::ST_NORMAL, val);
+        canvas.draw_str(x, y, select(super::ST_NORMAL, selected), val);
     }
 }
@@ -0,0 +1,300 @@
+use anyhow::{anyhow, Error, Result};
+use std::rc::Rc;
+use zi::{
+    components::text::{Text, TextAlign, TextProperties},
+    prelude::*,
+    Callback,
+};
+
+use crate::{
+    bus::{CpuMode, Regs},
+    target::Target,
+    tui::{PaneStatus, Scroll},
+    x86::dec::{call_before, CallKind},
+};
+
+const MAX_FRAMES: usize = 9; // number of saved BPs followed up the stack
+
+#[derive(Clone)]
+pub struct Properties {
+    pub status: PaneStatus,
+    pub target: Rc<dyn Target>,
+    pub regs: Regs,
+    /// Where to start, the stack has no selection.
+    pub scroll: Scroll,
+    pub on_scroll: Callback<Scroll>,
+}
+
+impl PartialEq for Properties {
+    fn eq(&self, other: &Properties) -> bool {
+        self.status == other.status && self.regs == other.regs
+    }
+}
+
+struct Row {
+    offset: u32,
+    value: u32,
+    /// `SP`, `BP` or `BPn` for the frame of the n-th caller.
+    mark: String,
+    /// Where the value returns to if it looks like a return address.
+    hint: Option<String>,
+}
+
+/// Words (or dwords) from SS:SP up.
+pub struct Stack {
+    props: Properties,
+    frame: Rect,
+    error: Option<Error>,
+    rows: Vec<Row>,
+    mode: CpuMode,
+    skip: usize,
+    /// SS:SP of the previous stop, everything below is pushed since then.
+    prev: Option<(u16, u32)>,
+}
+
+pub enum Message {
+    Up,
+    Down,
+}
+
+impl Stack {
+    fn load(&mut self) -> Result<()> {
+        let regs = &self.props.regs;
+        let target = &*self.props.target;
+
+        self.mode = target.cpu_mode()?;
+
+        let (size, mask) = if self.mode.stack_big {
+            (4, u32::MAX)
+        } else {
+            (2, 0xFFFF)
+        };
+
+        let sp = regs.esp & mask;
+        let start = sp.saturating_add((self.skip * size) as u32).min(mask);
+        // one extra value for the segment of a far return address on the last row
+        let count = self.frame.height() + 1;
+        let len = ((count * size) as u32).min((mask - start).saturating_add(1));
+        let data = target.read_mem(self.mode.address(regs.ss, start), len)?;
+
+        let values: Vec<u32> = data
+            .chunks_exact(size)
+            .map(|c| c.iter().rev().fold(0, |a, b| (a << 8) | u32::from(*b)))
+            .collect();
+
+        // saved BPs up to the first one that doesn't lead further up the stack
+        let mut frames = Vec::new();
+        let mut bp = regs.ebp & mask;
+
+        while frames.len() < MAX_FRAMES && bp >= sp {
+            frames.push(bp);
+
+            let saved = target.read_mem(self.mode.address(regs.ss, bp), size as u32)?;
+            let saved = saved.iter().rev().fold(0, |a, b| (a << 8) | u32::from(*b));
+
+            if saved <= bp {
+                break;
+            }
+
+            bp = saved;
+        }
+
+        self.rows.clear();
+
+        for (i, value) in values.iter().enumerate().take(self.frame.height()) {
+            let offset = start + (i * size) as u32;
+
+            let mut mark = Vec::new();
+
+            if offset == sp {
+                mark.push("SP".to_owned());
+            }
+
+            match frames.iter().position(|f| *f == offset) {
+                Some(0) => mark.push("BP".to_owned()),
+                Some(n) => mark.push(format!("BP{n}")),
+                None => (),
+            }
+
+            self.rows.push(Row {
+                offset,
+                value: *value,
+                mark: mark.join(" "),
+                hint: self.hint(*value, values.get(i + 1).copied()),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Tells whether the value (and the next one as a segment) is an address right after a call.
+    ///
+    /// Most values aren't valid selectors in protected mode, memory that can't be read
+    /// means no hint.
+    fn hint(&self, value: u32, next: Option<u32>) -> Option<String> {
+        let target = &*self.props.target;
+        let bitness = if self.mode.big { 32 } else { 16 };
+
+        if let Some(segment) = next.filter(|s| *s <= 0xFFFF) {
+            let addr = self.mode.address(segment as u16, value);
+
+            match call_before(target, addr, bitness) {
+                Ok(Some((CallKind::Far, _))) => return Some(format!("far ret {addr}")),
+                Ok(Some((CallKind::Interrupt, _))) => return Some(format!("iret {addr}")),
+                _ => (),
+            }
+        }
+
+        let addr = self.mode.address(self.props.regs.cs, value);
+
+        match call_before(target, addr, bitness) {
+            Ok(Some((CallKind::Near, _))) => Some(format!("ret {addr}")),
+            _ => None,
+        }
+    }
+
+    fn reload(&mut self) {
+        self.error = if !self.props.status.attached {
+            Some(anyhow!("Not attached."))
+        } else {
+            self.load().err()
+        };
+    }
+}
+
+impl Component for Stack {
+    type Message = Message;
+    type Properties = Properties;
+
+    fn create(props: Self::Properties, frame: Rect, _: ComponentLink<Self>) -> Self {
+        let skip = props.scroll.skip;
+        let mut stack = Self {
+            props,
+            frame,
+            error: None,
+            rows: Vec::new(),
+            mode: Default::default(),
+            skip,
+            prev: None,
+        };
+
+        stack.reload();
+        stack
+    }
+
+    fn change(&mut self, props: Self::Properties) -> ShouldRender {
+        if self.props == props {
+            return false.into();
+        }
+
+        if self.props.regs != props.regs {
+            self.prev = Some((self.props.regs.ss, self.props.regs.esp));
+            self.skip = 0;
+        }
+
+        self.props = props;
+        self.reload();
+
+        true.into()
+    }
+
+    fn update(&mut self, message: Self::Message) -> ShouldRender {
+        if !self.props.status.attached {
+            return false.into();
+        }
+
+        let prev = self.skip;
+
+        match message {
+            Message::Up if self.skip > 0 => self.skip -= 1,
+            Message::Down if self.rows.len() >= self.frame.height() => self.skip += 1,
+            _ => (),
+        }
+
+        if self.skip == prev {
+            return false.into();
+        }
+
+        self.props.on_scroll.emit(Scroll {
+            skip: self.skip,
+            pos: 0,
+        });
+        self.reload();
+
+        true.into()
+    }
+
+    fn bindings(&self, bind: &mut Bindings<Self>) {
+        bind.set_focus(self.props.status.focused);
+
+        if !bind.is_empty() {
+            return;
+        }
+
+        bind.command("up", || Message::Up).with([Key::Up]);
+        bind.command("down", || Message::Down).with([Key::Down]);
+    }
+
+    fn resize(&mut self, frame: Rect) -> ShouldRender {
+        self.frame = frame;
+        self.reload();
+
+        true.into()
+    }
+
+    fn view(&self) -> Layout {
+        if let Some(e) = &self.error {
+            return Text::with(
+                TextProperties::new()
+                    .style(super::ST_NORMAL)
+                    .align(TextAlign::Centre)
+                    .content(e.to_string()),
+            );
+        }
+
+        let mut canvas = Canvas::new(self.frame.size);
+        canvas.clear(super::ST_NORMAL);
+
+        let regs = &self.props.regs;
+        let (mask, digits) = if self.mode.stack_big {
+            (u32::MAX, 8)
+        } else {
+            (0xFFFF, 4)
+        };
+        let sp = regs.esp & mask;
+
+        for (y, row) in self.rows.iter().enumerate() {
+            let pushed = self.prev.is_some_and(|(ss, prev)| {
+                ss == regs.ss && row.offset >= sp && row.offset < prev & mask
+            });
+
+            let addr = format!("{:04X}:{:0digits$X}", regs.ss, row.offset);
+            let value = format!("{:0digits$X}", row.value);
+            let mut x = 0;
+
+            canvas.draw_str(x, y, super::ST_CAPTION, &addr);
+            x += addr.len() + 1;
+
+            canvas.draw_str(
+                x,
+                y,
+                if pushed {
+                    super::ST_CHANGED
+                } else {
+                    super::ST_NORMAL
+                },
+                &value,
+            );
+            x += value.len() + 1;
+
+            canvas.draw_str(x, y, super::ST_ACTIVE, &row.mark);
+            x += 6;
+
+            if let Some(hint) = &row.hint {
+                canvas.draw_str(x, y, super::ST_CAPTION, hint);
+            }
+        }
+
+        canvas.into()
+    }
+}
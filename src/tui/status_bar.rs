@@ -6,13 +6,21 @@ use zi::{
 const ATTACHED: char = '🟢';
 const DETACHED: char = '⛔';
 const STYLE: Style = Style::normal(super::BG_GRAY, super::FG_GRAY);
+/// Keys of the commands that drive the CPU, shown while it waits for one.
+const KEYS: &str = "run (F5)  step over (F10)  step in (F11)  step out (Ctrl-O)  break (F12)";
 
 #[derive(Clone, PartialEq)]
 pub enum Status {
-    Attached,
+    Attached(Option<String>),
     Detached(Option<String>),
 }
 
+impl Status {
+    pub fn is_attached(&self) -> bool {
+        matches!(self, Self::Attached(_))
+    }
+}
+
 pub struct StatusBar {
     status: Status,
 }
@@ -39,7 +47,8 @@ impl Component for StatusBar {
     fn view(&self) -> Layout {
         let tc = TextProperties::new().style(STYLE);
         let tc = match &self.status {
-            Status::Attached => tc.content(format!(" {ATTACHED} Ready")),
+            Status::Attached(None) => tc.content(format!(" {ATTACHED} Ready    {KEYS}")),
+            Status::Attached(Some(message)) => tc.content(format!(" {ATTACHED} {message}")),
             Status::Detached(None) => tc.content(format!(" {DETACHED} Detached")),
             Status::Detached(Some(reason)) => tc.content(format!(" {DETACHED} {reason}")),
         };
@@ -1,32 +1,39 @@
-use anyhow::Result;
-use iced_x86::{Code, Decoder, DecoderOptions, Instruction};
-
-use super::Address;
-use crate::bus::Proxy;
+use anyhow::{bail, Result};
+use iced_x86::{Code, Decoder, DecoderOptions, FlowControl, Instruction};
+
+use super::{
+    desc::{self, Descriptor, Kind},
+    Address,
+};
+use crate::{
+    bus::{CpuMode, Regs},
+    target::Target,
+};
 
 const DECODER_OPTIONS: u32 = DecoderOptions::NONE;
 const MAX_INSTR_LEN: usize = 15; // maximal instruction length (don't load less than that)
 const SKIP_INSTR_LEN: usize = MAX_INSTR_LEN * 3; // number of bytes to skip as a few instructions might be corrupted
 const FETCH_ATTEMPT_NUM: usize = 5; // number of attempts to decode instructions
-const BITNESS: u32 = 16;
+const MAX_CALL_LEN: u32 = 8; // far call with an operand size prefix
+const STACK_SCAN_LEN: u32 = 0x80; // number of bytes above SP searched for a return address
+const MAX_FRAMES: usize = 32; // number of callers followed up the stack
 
 pub fn fetch_before(
-    proxy: &Proxy,
+    target: &dyn Target,
     addr: Address,
     limit: usize,
 ) -> Result<Vec<(Instruction, Vec<u8>)>> {
+    let bitness = Sizes::code_at(target, addr)?;
     let mut code: Vec<(Instruction, Vec<u8>)> = Vec::new();
 
     for attempt in 1..=FETCH_ATTEMPT_NUM {
-        let first = code.first().map(|(i, _)| i.ip32()).unwrap_or(addr.offset);
+        let first = code.first().map(|(i, _)| i.ip32()).unwrap_or(addr.offset());
         let start = first.saturating_sub(
             (attempt * SKIP_INSTR_LEN + limit.saturating_sub(code.len()) * MAX_INSTR_LEN) as u32,
         );
 
-        let data = proxy
-            .mem
-            .get(addr.segment, start, first.saturating_sub(start))?;
-        let mut dec = Decoder::with_ip(BITNESS, &data, start.into(), DECODER_OPTIONS);
+        let data = target.read_mem(addr.with_offset(start), first.saturating_sub(start))?;
+        let mut dec = Decoder::with_ip(bitness, &data, start.into(), DECODER_OPTIONS);
 
         let Some(mut ins) = (0..data.len()).find_map(|skip| {
             let tmp: Vec<_> = dec
@@ -77,11 +84,12 @@ pub fn fetch_before(
 }
 
 pub fn fetch_after(
-    proxy: &Proxy,
+    target: &dyn Target,
     addr: Address,
     limit: usize,
 ) -> Result<Vec<(Instruction, Vec<u8>)>> {
-    let max = if BITNESS == 16 {
+    let bitness = Sizes::code_at(target, addr)?;
+    let max = if bitness == 16 {
         u16::MAX as u32
     } else {
         u32::MAX
@@ -93,15 +101,13 @@ pub fn fetch_after(
         let start = code
             .last()
             .map(|(i, _)| i.next_ip32())
-            .unwrap_or(addr.offset);
+            .unwrap_or(addr.offset());
         let end = max.min(start.saturating_add(
             (attempt * SKIP_INSTR_LEN + limit.saturating_sub(code.len()) * MAX_INSTR_LEN) as u32,
         ));
 
-        let data = proxy
-            .mem
-            .get(addr.segment, start, end.saturating_sub(start))?;
-        let mut dec = Decoder::with_ip(BITNESS, &data, start.into(), DECODER_OPTIONS);
+        let data = target.read_mem(addr.with_offset(start), end.saturating_sub(start))?;
+        let mut dec = Decoder::with_ip(bitness, &data, start.into(), DECODER_OPTIONS);
 
         while dec.can_decode() {
             let pos = dec.position();
@@ -133,11 +139,314 @@ pub fn fetch_after(
     Ok(code)
 }
 
-pub fn step_over(proxy: &Proxy, addr: Address) -> Result<()> {
-    let data = proxy
-        .mem
-        .get(addr.segment, addr.offset, (MAX_INSTR_LEN * 2) as u32)?;
-    let mut dec = Decoder::with_ip(BITNESS, &data, addr.offset as u64, DECODER_OPTIONS);
+/// Sizes the code is decoded and the stack is read with, by the current CS and SS.
+///
+/// Code at a protected mode address is decoded by the D bit of its own selector, see `code_at`.
+#[derive(Clone, Copy)]
+struct Sizes {
+    /// Bitness of the code.
+    code: u32,
+    /// Size of a value pushed onto the stack, in bytes.
+    stack: usize,
+    mode: CpuMode,
+}
+
+impl Sizes {
+    fn get(target: &dyn Target) -> Result<Self> {
+        let mode = target.cpu_mode()?;
+
+        Ok(Self {
+            code: if mode.big { 32 } else { 16 },
+            stack: if mode.stack_big { 4 } else { 2 },
+            mode,
+        })
+    }
+
+    /// Bitness of the code at `addr`, which may be in a segment other than CS.
+    fn code_at(target: &dyn Target, addr: Address) -> Result<u32> {
+        // the current CS is the best guess when the selector can't be looked up
+        if let Address::Prot(selector, _) = addr {
+            if let Ok(Descriptor {
+                kind: Kind::Code { big, .. },
+                ..
+            }) = desc::lookup(target, selector)
+            {
+                return Ok(if big { 32 } else { 16 });
+            }
+        }
+
+        Ok(Self::get(target)?.code)
+    }
+
+    fn stack_mask(self) -> u32 {
+        if self.stack == 4 {
+            u32::MAX
+        } else {
+            0xFFFF
+        }
+    }
+
+    fn stack_value(self, data: &[u8]) -> u32 {
+        data.iter()
+            .take(self.stack)
+            .rev()
+            .fold(0, |a, b| (a << 8) | u32::from(*b))
+    }
+}
+
+/// How the routine a return address leads back from was entered.
+#[derive(Clone, Copy, PartialEq)]
+pub enum CallKind {
+    Near,
+    Far,
+    Interrupt,
+}
+
+/// Decodes the `bitness` code right before `addr` and tells which call, if any, ends there.
+///
+/// A value on the stack followed by such an instruction most likely is a return address.
+pub fn call_before(
+    target: &dyn Target,
+    addr: Address,
+    bitness: u32,
+) -> Result<Option<(CallKind, Instruction)>> {
+    let start = addr.offset().saturating_sub(MAX_CALL_LEN);
+    let data = target.read_mem(addr.with_offset(start), addr.offset() - start)?;
+
+    for skip in 0..data.len() {
+        let mut dec = Decoder::with_ip(
+            bitness,
+            &data[skip..],
+            start as u64 + skip as u64,
+            DECODER_OPTIONS,
+        );
+        let ins = dec.decode();
+
+        if ins.is_invalid() || ins.next_ip32() != addr.offset() {
+            continue;
+        }
+
+        if ins.is_call_near() || ins.is_call_near_indirect() {
+            return Ok(Some((CallKind::Near, ins)));
+        }
+
+        if ins.is_call_far() || ins.is_call_far_indirect() {
+            return Ok(Some((CallKind::Far, ins)));
+        }
+
+        if ins.code() == Code::Int_imm8 {
+            return Ok(Some((CallKind::Interrupt, ins)));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Return address found on the stack.
+pub struct Frame {
+    pub ret: Address,
+    pub kind: CallKind,
+    /// The instruction right before the return address.
+    pub call: Instruction,
+    /// Stack pointer once the routine has returned.
+    pub sp: u32,
+}
+
+/// Finds the first of `slots` values from `ss:sp` up that follows a near call in `cs`,
+/// a far call or an `int` instruction.
+fn find_return(
+    target: &dyn Target,
+    (ss, cs): (u16, u16),
+    sp: u32,
+    slots: usize,
+    sizes: Sizes,
+) -> Result<Option<Frame>> {
+    let size = sizes.stack;
+    // the segment and flags of the last slot are needed too
+    let stack = target.read_mem(sizes.mode.address(ss, sp), ((slots + 2) * size) as u32)?;
+    let value = |i: usize| sizes.stack_value(&stack[i..]);
+
+    for i in (0..stack.len().saturating_sub(size - 1).min(slots * size)).step_by(size) {
+        let offset = value(i);
+
+        if i + size * 2 <= stack.len() {
+            let ret = sizes.mode.address(value(i + size) as u16, offset);
+
+            // memory that can't be read holds no call, so the value isn't a return address
+            let len = match call_before(target, ret, sizes.code).unwrap_or(None) {
+                Some((kind @ CallKind::Far, call)) => Some((kind, call, 2)),
+                Some((kind @ CallKind::Interrupt, call)) if i + size * 3 <= stack.len() => {
+                    Some((kind, call, 3))
+                }
+                _ => None,
+            };
+
+            if let Some((kind, call, len)) = len {
+                let sp = sp + (i + size * len) as u32;
+                return Ok(Some(Frame {
+                    ret,
+                    kind,
+                    call,
+                    sp,
+                }));
+            }
+        }
+
+        let ret = sizes.mode.address(cs, offset);
+
+        if let Ok(Some((kind @ CallKind::Near, call))) = call_before(target, ret, sizes.code) {
+            let sp = sp + (i + size) as u32;
+            return Ok(Some(Frame {
+                ret,
+                kind,
+                call,
+                sp,
+            }));
+        }
+    }
+
+    Ok(None)
+}
+
+/// Finds the return address right above the BP saved at `ss:bp`, `push bp` puts it there.
+///
+/// Returns the frame and the saved BP, which leads to the frame of the caller.
+fn bp_frame(
+    target: &dyn Target,
+    (ss, cs): (u16, u16),
+    bp: u32,
+    sp: u32,
+    sizes: Sizes,
+) -> Result<Option<(Frame, u32)>> {
+    let size = sizes.stack as u32;
+
+    if bp < sp || bp > sizes.stack_mask() - size * 4 {
+        return Ok(None);
+    }
+
+    let saved = target.read_mem(sizes.mode.address(ss, bp), size)?;
+
+    Ok(find_return(target, (ss, cs), bp + size, 1, sizes)?.map(|f| (f, sizes.stack_value(&saved))))
+}
+
+/// Walks the callers up the stack.
+///
+/// Frames are followed by the saved BPs, and where the chain is broken (or
+/// there is none) the stack is scanned for the next return address.
+pub fn call_stack(target: &dyn Target, regs: &Regs) -> Result<Vec<Frame>> {
+    let sizes = Sizes::get(target)?;
+    let mask = sizes.stack_mask();
+
+    let mut frames: Vec<Frame> = Vec::new();
+    let mut sp = regs.esp & mask;
+    let mut bp = regs.ebp & mask;
+
+    // at the entry of a routine BP still belongs to the caller
+    if let Some(f) = find_return(target, (regs.ss, regs.cs), sp, 1, sizes)? {
+        sp = f.sp;
+        frames.push(f);
+    }
+
+    while frames.len() < MAX_FRAMES {
+        let cs = frames
+            .last()
+            .and_then(|f| f.ret.segment())
+            .unwrap_or(regs.cs);
+
+        let chained = bp_frame(target, (regs.ss, cs), bp, sp, sizes)?;
+        let scan = STACK_SCAN_LEN as usize / sizes.stack;
+
+        let frame = match chained {
+            Some((f, saved)) => {
+                bp = saved;
+                f
+            }
+            None => match find_return(target, (regs.ss, cs), sp, scan, sizes)? {
+                Some(f) => f,
+                None => break,
+            },
+        };
+
+        sp = frame.sp;
+        frames.push(frame);
+    }
+
+    Ok(frames)
+}
+
+/// Instruction bytes replaced with `int3` to stop the CPU when it gets there.
+pub struct Trap {
+    addr: Address,
+    data: Vec<u8>,
+    /// Stack pointer the CPU has to be at or above, so a deeper frame of a recursion passes by.
+    sp: Option<u32>,
+    stack_mask: u32,
+}
+
+impl Trap {
+    pub fn addr(&self) -> Address {
+        self.addr
+    }
+
+    pub fn set(target: &dyn Target, addr: Address, len: usize, sp: Option<u32>) -> Result<Self> {
+        let stack_mask = Sizes::get(target)?.stack_mask();
+        let mut data = Vec::with_capacity(len);
+
+        for i in 0..len as u32 {
+            data.push(target.write_mem(addr.with_offset(addr.offset() + i), 0xCC)?);
+        }
+
+        Ok(Self {
+            addr,
+            data,
+            sp: sp.map(|sp| sp & stack_mask),
+            stack_mask,
+        })
+    }
+
+    pub fn restore(&self, target: &dyn Target) -> Result<()> {
+        for (i, d) in (0u32..).zip(&self.data) {
+            target.write_mem(self.addr.with_offset(self.addr.offset() + i), *d)?;
+        }
+
+        Ok(())
+    }
+
+    /// Tells whether the stop at the trap is the one it was set for.
+    pub fn reached(&self, regs: &Regs) -> bool {
+        let Some(sp) = self.sp else {
+            return true;
+        };
+
+        // the stack may wrap around, so the distance is what matters
+        let distance = (regs.esp & self.stack_mask).wrapping_sub(sp);
+
+        if self.stack_mask == 0xFFFF {
+            distance as u16 as i16 >= 0
+        } else {
+            distance as i32 >= 0
+        }
+    }
+
+    /// Executes the instruction under the trap and sets it again.
+    pub fn pass(&mut self, target: &dyn Target) -> Result<()> {
+        self.restore(target)?;
+        target.step_in()?;
+
+        *self = Self::set(target, self.addr, self.data.len(), self.sp)?;
+
+        Ok(())
+    }
+}
+
+/// Steps over calls, loops, repeated string instructions and interrupts.
+///
+/// Returns the trap set after such an instruction, the target is running then
+/// and the trap has to be restored once it breaks.
+pub fn step_over(target: &dyn Target, addr: Address) -> Result<Option<Trap>> {
+    let bitness = Sizes::code_at(target, addr)?;
+    let data = target.read_mem(addr, (MAX_INSTR_LEN * 2) as u32)?;
+    let mut dec = Decoder::with_ip(bitness, &data, addr.offset() as u64, DECODER_OPTIONS);
 
     let mut ins = Instruction::default();
     dec.decode_out(&mut ins);
@@ -151,28 +460,185 @@ pub fn step_over(proxy: &Proxy, addr: Address) -> Result<()> {
         || ins.has_repne_prefix()
         || ins.code() == Code::Int_imm8)
     {
-        proxy.cpu.step_in()?;
+        target.step_in()?;
 
-        return Ok(());
+        return Ok(None);
     }
 
     dec.decode_out(&mut ins);
 
     if ins.is_invalid() {
-        return Ok(());
+        return Ok(None);
+    }
+
+    let trap = Trap::set(target, addr.with_offset(ins.ip32()), ins.len(), None)?;
+
+    target.run()?;
+
+    Ok(Some(trap))
+}
+
+/// Runs until the current routine returns to its caller.
+///
+/// The return address is a value that follows a near call in the current code segment,
+/// a far call or an `int` instruction. It's taken right at SP at the entry of the routine,
+/// then above the BP the routine saved, and only when there is no such frame the stack is
+/// scanned up from SP. The trap set there only counts once SP is above that value, so
+/// recursive calls don't stop early.
+pub fn step_out(target: &dyn Target, regs: &Regs) -> Result<Option<Trap>> {
+    let sizes = Sizes::get(target)?;
+    let ip = sizes.mode.address(regs.cs, regs.eip);
+    let data = target.read_mem(ip, MAX_INSTR_LEN as u32)?;
+    let mut dec = Decoder::with_ip(sizes.code, &data, regs.eip.into(), DECODER_OPTIONS);
+
+    // at the very end of the routine already
+    if dec.decode().flow_control() == FlowControl::Return {
+        target.step_in()?;
+
+        return Ok(None);
+    }
+
+    let sp = regs.esp & sizes.stack_mask();
+    let bp = regs.ebp & sizes.stack_mask();
+    let scan = STACK_SCAN_LEN as usize / sizes.stack;
+    let segments = (regs.ss, regs.cs);
+
+    // a value further up may only look like a return address, so the frame goes first
+    let frame = match find_return(target, segments, sp, 1, sizes)? {
+        Some(f) => Some(f),
+        None => match bp_frame(target, segments, bp, sp, sizes)? {
+            Some((f, _)) => Some(f),
+            None => find_return(target, segments, sp, scan, sizes)?,
+        },
+    };
+
+    match frame {
+        Some(f) => run_to(target, f.ret, Some(f.sp)),
+        None => bail!("No return address found on the stack."),
+    }
+}
+
+/// Runs until the CPU gets to `addr`, with the stack pointer at or above `sp` if given.
+pub fn run_to(target: &dyn Target, addr: Address, sp: Option<u32>) -> Result<Option<Trap>> {
+    let bitness = Sizes::code_at(target, addr)?;
+    let data = target.read_mem(addr, MAX_INSTR_LEN as u32)?;
+    let mut dec = Decoder::with_ip(bitness, &data, addr.offset().into(), DECODER_OPTIONS);
+    let ins = dec.decode();
+
+    let trap = Trap::set(target, addr, ins.len().max(1), sp)?;
+
+    target.run()?;
+
+    Ok(Some(trap))
+}
+
+#[cfg(test)]
+mod tests {
+    use iced_x86::Mnemonic;
+
+    use super::*;
+    use crate::fake::Machine;
+
+    // mov ax, 1234h / nop / int 21h / ret, loaded at 1000:0100
+    const CODE: &[u8] = &[0xB8, 0x34, 0x12, 0x90, 0xCD, 0x21, 0xC3];
+
+    fn at(offset: u32) -> Address {
+        Address::Real(0x1000, offset)
+    }
+
+    fn mnemonics(code: &[(Instruction, Vec<u8>)]) -> Vec<Mnemonic> {
+        code.iter().map(|(i, _)| i.mnemonic()).collect()
+    }
+
+    #[test]
+    fn fetch_after_decodes_in_order() {
+        let machine = Machine::new(CODE);
+        let code = fetch_after(&machine, at(0x100), 4).unwrap();
+
+        assert_eq!(
+            mnemonics(&code[..4]),
+            [Mnemonic::Mov, Mnemonic::Nop, Mnemonic::Int, Mnemonic::Ret]
+        );
+        assert_eq!(code[0].0.ip32(), 0x100);
+        assert_eq!(code[0].1, [0xB8, 0x34, 0x12]);
+        assert_eq!(code[2].0.ip32(), 0x104);
+        assert_eq!(code[2].1, [0xCD, 0x21]);
     }
 
-    let mut data = [0u8; MAX_INSTR_LEN];
+    #[test]
+    fn fetch_after_stops_at_the_end_of_the_segment() {
+        let machine = Machine::new(CODE);
+        let code = fetch_after(&machine, at(0xFFF0), 100).unwrap();
 
-    for (i, d) in data.iter_mut().enumerate().take(ins.len()) {
-        *d = proxy.mem.set(addr.segment, ins.ip32() + i as u32, 0xCC)?;
+        assert!(!code.is_empty());
+        assert!(code.iter().all(|(i, _)| i.next_ip32() <= 0x10000));
     }
 
-    proxy.cpu.run()?;
+    #[test]
+    fn fetch_before_ends_right_before_the_address() {
+        let machine = Machine::new(CODE);
+        let code = fetch_before(&machine, at(0x106), 3).unwrap();
 
-    for (i, d) in data.iter().enumerate().take(ins.len()) {
-        proxy.mem.set(addr.segment, ins.ip32() + i as u32, *d)?;
+        assert!(code.len() >= 3);
+        assert_eq!(code.last().unwrap().0.next_ip32(), 0x106);
+        assert_eq!(
+            mnemonics(&code[code.len() - 3..]),
+            [Mnemonic::Mov, Mnemonic::Nop, Mnemonic::Int]
+        );
+        assert_eq!(code[code.len() - 3].1, [0xB8, 0x34, 0x12]);
     }
 
-    Ok(())
+    #[test]
+    fn code_bitness_follows_the_selector() {
+        let machine = Machine::new(CODE);
+        // null, flat 32-bit code
+        machine.load_gdt(&[[0; 8], 0x00CF_9A00_0000_FFFFu64.to_le_bytes()].concat());
+
+        let bitness = |addr| Sizes::code_at(&machine, addr).unwrap();
+
+        assert_eq!(bitness(Address::Prot(8, 0)), 32);
+        assert_eq!(bitness(Address::Prot(0xB, 0)), 32);
+        // beyond the GDT and in the empty LDT, the current CS tells
+        assert_eq!(bitness(Address::Prot(0x10, 0)), 16);
+        assert_eq!(bitness(Address::Prot(0xC, 0)), 16);
+        assert_eq!(bitness(at(0)), 16);
+    }
+
+    #[test]
+    fn step_out_follows_the_bp_frame() {
+        // call 0110 / nop / nop / call 0110
+        let machine = Machine::new(&[0xE8, 0x0D, 0x00, 0x90, 0x90, 0xE8, 0x08, 0x00]);
+        let mut regs = machine.regs().unwrap();
+
+        // a local, a pointer that looks like a return address, the saved BP and the return address
+        let stack = [0x00, 0x00, 0x08, 0x01, 0xFA, 0xFF, 0x03, 0x01];
+
+        for (i, b) in (0xFFEE..).zip(stack) {
+            machine.write_mem(at(i), b).unwrap();
+        }
+
+        regs.eip = 0x110;
+        regs.esp = 0xFFEE;
+        regs.ebp = 0xFFF2;
+
+        let trap = step_out(&machine, &regs).unwrap().unwrap();
+        assert!(trap.addr() == at(0x103));
+
+        // no frame, the first value that looks like a return address
+        regs.ebp = 0;
+
+        let trap = step_out(&machine, &regs).unwrap().unwrap();
+        assert!(trap.addr() == at(0x108));
+    }
+
+    #[test]
+    fn fetch_before_the_start_of_the_segment() {
+        let machine = Machine::new(CODE);
+
+        assert!(fetch_before(&machine, at(0), 5).unwrap().is_empty());
+
+        // `int 20h` DOS puts at PSP:0000
+        let code = fetch_before(&machine, at(2), 5).unwrap();
+        assert_eq!(mnemonics(&code), [Mnemonic::Int]);
+    }
 }
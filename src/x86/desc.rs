@@ -0,0 +1,212 @@
+use anyhow::Result;
+use std::fmt;
+
+use super::Address;
+use crate::target::Target;
+
+/// Size of a protected mode descriptor.
+const DESCRIPTOR_SIZE: usize = 8;
+
+/// Size of a real mode interrupt vector.
+const VECTOR_SIZE: usize = 4;
+
+#[derive(Clone, Copy, Default, PartialEq)]
+pub enum Table {
+    #[default]
+    Gdt,
+    Ldt,
+    /// The interrupt vector table in real mode.
+    Idt,
+}
+
+impl Table {
+    pub fn next(self) -> Self {
+        match self {
+            Self::Gdt => Self::Ldt,
+            Self::Ldt => Self::Idt,
+            Self::Idt => Self::Gdt,
+        }
+    }
+
+    /// Selector of the n-th entry, the vector number for the IDT.
+    pub fn selector(self, index: usize) -> u16 {
+        match self {
+            Self::Gdt => (index * DESCRIPTOR_SIZE) as u16,
+            Self::Ldt => (index * DESCRIPTOR_SIZE) as u16 | 4,
+            Self::Idt => index as u16,
+        }
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Gdt => "GDT",
+            Self::Ldt => "LDT",
+            Self::Idt => "IDT",
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+    Null,
+    Code {
+        base: u32,
+        limit: u32,
+        big: bool,
+        conforming: bool,
+        readable: bool,
+    },
+    Data {
+        base: u32,
+        limit: u32,
+        big: bool,
+        expand_down: bool,
+        writable: bool,
+    },
+    /// An LDT or a TSS.
+    System {
+        name: &'static str,
+        base: u32,
+        limit: u32,
+    },
+    /// A call, interrupt, trap or task gate, the latter has no offset.
+    Gate {
+        name: &'static str,
+        selector: u16,
+        offset: u32,
+        params: u8,
+    },
+    Reserved(u8),
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Descriptor {
+    pub kind: Kind,
+    pub dpl: u8,
+    pub present: bool,
+}
+
+impl Descriptor {
+    pub fn decode(raw: u64) -> Self {
+        let dpl = ((raw >> 45) & 3) as u8;
+        let present = raw & (1 << 47) != 0;
+
+        if raw == 0 {
+            return Self {
+                kind: Kind::Null,
+                dpl,
+                present,
+            };
+        }
+
+        let typ = ((raw >> 40) & 0xF) as u8;
+        let base = ((raw >> 16) & 0xFF_FFFF) as u32 | ((raw >> 32) & 0xFF00_0000) as u32;
+        let limit = (raw & 0xFFFF) as u32 | ((raw >> 32) & 0xF_0000) as u32;
+        let limit = if raw & (1 << 55) != 0 {
+            (limit << 12) | 0xFFF
+        } else {
+            limit
+        };
+        let big = raw & (1 << 54) != 0;
+
+        let gate = |name, wide: bool| Kind::Gate {
+            name,
+            selector: (raw >> 16) as u16,
+            offset: if wide {
+                (raw & 0xFFFF) as u32 | ((raw >> 32) & 0xFFFF_0000) as u32
+            } else {
+                (raw & 0xFFFF) as u32
+            },
+            params: ((raw >> 32) & 0x1F) as u8,
+        };
+
+        let kind = if raw & (1 << 44) != 0 {
+            if typ & 8 != 0 {
+                Kind::Code {
+                    base,
+                    limit,
+                    big,
+                    conforming: typ & 4 != 0,
+                    readable: typ & 2 != 0,
+                }
+            } else {
+                Kind::Data {
+                    base,
+                    limit,
+                    big,
+                    expand_down: typ & 4 != 0,
+                    writable: typ & 2 != 0,
+                }
+            }
+        } else {
+            match typ {
+                0x1 => Kind::System {
+                    name: "tss16",
+                    base,
+                    limit,
+                },
+                0x2 => Kind::System {
+                    name: "ldt",
+                    base,
+                    limit,
+                },
+                0x3 => Kind::System {
+                    name: "tss16b",
+                    base,
+                    limit,
+                },
+                0x4 => gate("call16", false),
+                0x5 => Kind::Gate {
+                    name: "task",
+                    selector: (raw >> 16) as u16,
+                    offset: 0,
+                    params: 0,
+                },
+                0x6 => gate("int16", false),
+                0x7 => gate("trap16", false),
+                0x9 => Kind::System {
+                    name: "tss32",
+                    base,
+                    limit,
+                },
+                0xB => Kind::System {
+                    name: "tss32b",
+                    base,
+                    limit,
+                },
+                0xC => gate("call32", true),
+                0xE => gate("int32", true),
+                0xF => gate("trap32", true),
+                _ => Kind::Reserved(typ),
+            }
+        };
+
+        Self { kind, dpl, present }
+    }
+
+    /// Splits a table into descriptors, a partial last one is dropped.
+    pub fn decode_table(data: &[u8]) -> Vec<Self> {
+        data.chunks_exact(DESCRIPTOR_SIZE)
+            .map(|c| Self::decode(u64::from_le_bytes(c.try_into().unwrap())))
+            .collect()
+    }
+}
+
+/// Reads the descriptor a selector refers to in the GDT or the LDT.
+pub fn lookup(target: &dyn Target, selector: u16) -> Result<Descriptor> {
+    Ok(Descriptor::decode(target.descriptor(selector)?))
+}
+
+/// Splits the real mode interrupt vector table into far pointers.
+pub fn decode_vectors(data: &[u8]) -> Vec<Address> {
+    data.chunks_exact(VECTOR_SIZE)
+        .map(|c| {
+            let offset = u16::from_le_bytes([c[0], c[1]]);
+            let segment = u16::from_le_bytes([c[2], c[3]]);
+
+            Address::Real(segment, offset.into())
+        })
+        .collect()
+}
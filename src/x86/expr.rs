@@ -0,0 +1,644 @@
+use anyhow::{anyhow, bail, Result};
+use std::{iter::Peekable, str::CharIndices};
+
+use super::Address;
+use crate::{bus::Regs, target::Target};
+
+/// Expression over registers and memory, e.g. `ax == 4C00 && byte [ds:si] == 0`.
+///
+/// Numbers are hexadecimal like in DEBUG.COM, `0x` prefix and `h` suffix are accepted too.
+/// A number that reads as a register, like `cf` or `ah`, needs a leading zero.
+/// Operators and their precedence follow Rust, comparisons yield 0 or 1.
+#[derive(Clone, PartialEq)]
+pub enum Expr {
+    Num(u32),
+    Reg(&'static str),
+    Unary(&'static str, Box<Expr>),
+    Binary(&'static str, Box<Expr>, Box<Expr>),
+    /// `size [segment:offset]`, the segment defaults to DS.
+    Mem(u32, Option<Box<Expr>>, Box<Expr>),
+}
+
+/// Address to go to, e.g. `es:di`, `ss:sp+4`, `#12345` (linear), `%12345` (physical)
+/// or `far [0:84]`.
+///
+/// A plain offset is taken in the segment of the pane. Segments are selectors in protected
+/// mode, unless `rm` or `pm` in front tells otherwise, e.g. `rm b800:0` from 32-bit code.
+#[derive(Clone, PartialEq)]
+pub enum Location {
+    Near(Option<SegmentKind>, Option<Expr>, Expr),
+    /// `far [segment:offset]`, a pointer stored in memory.
+    Far(Expr),
+    Linear(Expr),
+    Physical(Expr),
+}
+
+/// How a segment of a location is resolved, when it isn't the way the CPU does it now.
+#[derive(Clone, Copy, PartialEq)]
+pub enum SegmentKind {
+    Real,
+    Prot,
+}
+
+const REGISTERS: &[&str] = &[
+    "eax", "ebx", "ecx", "edx", "esi", "edi", "ebp", "esp", "eip", "ax", "bx", "cx", "dx", "si",
+    "di", "bp", "sp", "ip", "al", "bl", "cl", "dl", "ah", "bh", "ch", "dh", "cs", "ds", "es", "fs",
+    "gs", "ss", "cf", "pf", "af", "zf", "sf", "tf", "if", "df", "of", "iopl", "nt", "vm", "ac",
+    "id", "efl",
+];
+
+// binary operators from the lowest precedence to the highest
+const BINARY_OPS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<=", ">=", "<", ">"],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["<<", ">>"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl Expr {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            text,
+            chars: text.char_indices().peekable(),
+        };
+
+        let expr = parser.binary(0)?;
+        parser.skip_spaces();
+
+        match parser.chars.peek() {
+            Some((pos, _)) => bail!("Unexpected `{}`.", &text[*pos..]),
+            None => Ok(expr),
+        }
+    }
+
+    pub fn eval(&self, regs: &Regs, target: &dyn Target) -> Result<u32> {
+        Ok(match self {
+            Self::Num(n) => *n,
+            Self::Reg(name) => register(regs, name),
+            Self::Unary(op, e) => {
+                let v = e.eval(regs, target)?;
+
+                match *op {
+                    "-" => v.wrapping_neg(),
+                    "!" => (v == 0).into(),
+                    _ => !v,
+                }
+            }
+            Self::Binary("&&", l, r) => {
+                (l.eval(regs, target)? != 0 && r.eval(regs, target)? != 0).into()
+            }
+            Self::Binary("||", l, r) => {
+                (l.eval(regs, target)? != 0 || r.eval(regs, target)? != 0).into()
+            }
+            Self::Binary(op, l, r) => {
+                let (l, r) = (l.eval(regs, target)?, r.eval(regs, target)?);
+
+                match *op {
+                    "==" => (l == r).into(),
+                    "!=" => (l != r).into(),
+                    "<=" => (l <= r).into(),
+                    ">=" => (l >= r).into(),
+                    "<" => (l < r).into(),
+                    ">" => (l > r).into(),
+                    "|" => l | r,
+                    "^" => l ^ r,
+                    "&" => l & r,
+                    "<<" => l.wrapping_shl(r),
+                    ">>" => l.wrapping_shr(r),
+                    "+" => l.wrapping_add(r),
+                    "-" => l.wrapping_sub(r),
+                    "*" => l.wrapping_mul(r),
+                    "/" | "%" if r == 0 => bail!("Division by zero."),
+                    "/" => l / r,
+                    _ => l % r,
+                }
+            }
+            Self::Mem(size, seg, off) => {
+                let seg = match seg {
+                    Some(s) => s.eval(regs, target)? as u16,
+                    None => regs.ds,
+                };
+                let off = off.eval(regs, target)?;
+                let addr = target.cpu_mode()?.address(seg, off);
+                let data = target.read_mem(addr, *size)?;
+
+                data.iter().rev().fold(0, |a, b| (a << 8) | u32::from(*b))
+            }
+        })
+    }
+}
+
+pub fn register(regs: &Regs, name: &str) -> u32 {
+    let r = regs;
+
+    match name {
+        "eax" => r.eax,
+        "ebx" => r.ebx,
+        "ecx" => r.ecx,
+        "edx" => r.edx,
+        "esi" => r.esi,
+        "edi" => r.edi,
+        "ebp" => r.ebp,
+        "esp" => r.esp,
+        "eip" => r.eip,
+        "ax" => r.eax & 0xFFFF,
+        "bx" => r.ebx & 0xFFFF,
+        "cx" => r.ecx & 0xFFFF,
+        "dx" => r.edx & 0xFFFF,
+        "si" => r.esi & 0xFFFF,
+        "di" => r.edi & 0xFFFF,
+        "bp" => r.ebp & 0xFFFF,
+        "sp" => r.esp & 0xFFFF,
+        "ip" => r.eip & 0xFFFF,
+        "al" => r.eax & 0xFF,
+        "bl" => r.ebx & 0xFF,
+        "cl" => r.ecx & 0xFF,
+        "dl" => r.edx & 0xFF,
+        "ah" => (r.eax >> 8) & 0xFF,
+        "bh" => (r.ebx >> 8) & 0xFF,
+        "ch" => (r.ecx >> 8) & 0xFF,
+        "dh" => (r.edx >> 8) & 0xFF,
+        "cs" => r.cs.into(),
+        "ds" => r.ds.into(),
+        "es" => r.es.into(),
+        "fs" => r.fs.into(),
+        "gs" => r.gs.into(),
+        "ss" => r.ss.into(),
+        "cf" => r.cf.into(),
+        "pf" => r.pf.into(),
+        "af" => r.af.into(),
+        "zf" => r.zf.into(),
+        "sf" => r.sf.into(),
+        "tf" => r.tf.into(),
+        "if" => r.r#if.into(),
+        "df" => r.df.into(),
+        "of" => r.of.into(),
+        "iopl" => r._iopl.into(),
+        "nt" => r._nt.into(),
+        "vm" => r._vm.into(),
+        "ac" => r._ac.into(),
+        "id" => r._id.into(),
+        "efl" => r.eflags(),
+        _ => 0,
+    }
+}
+
+impl Location {
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut parser = Parser {
+            text,
+            chars: text.char_indices().peekable(),
+        };
+
+        let kind = match parser.clone().word().as_str() {
+            "rm" => Some(SegmentKind::Real),
+            "pm" => Some(SegmentKind::Prot),
+            _ => None,
+        };
+
+        if kind.is_some() {
+            parser.word();
+        }
+
+        let location = if kind.is_none() && parser.eat("#") {
+            Self::Linear(parser.binary(0)?)
+        } else if kind.is_none() && parser.eat("%") {
+            Self::Physical(parser.binary(0)?)
+        } else if kind.is_none() && parser.clone().word() == "far" {
+            parser.word();
+            parser.expect("[")?;
+
+            Self::Far(parser.memory(4)?)
+        } else {
+            let first = parser.binary(0)?;
+
+            if parser.eat(":") {
+                Self::Near(kind, Some(first), parser.binary(0)?)
+            } else {
+                Self::Near(kind, None, first)
+            }
+        };
+
+        parser.skip_spaces();
+
+        match parser.chars.peek() {
+            Some((pos, _)) => bail!("Unexpected `{}`.", &text[*pos..]),
+            None => Ok(location),
+        }
+    }
+
+    pub fn eval(&self, regs: &Regs, target: &dyn Target, segment: u16) -> Result<Address> {
+        let mode = target.cpu_mode()?;
+
+        Ok(match self {
+            Self::Near(kind, seg, off) => {
+                let seg = match seg {
+                    Some(s) => s.eval(regs, target)?,
+                    None => segment.into(),
+                };
+
+                if seg > 0xFFFF {
+                    bail!("Segment value {seg:X} is too large.");
+                }
+
+                let (seg, off) = (seg as u16, off.eval(regs, target)?);
+
+                match kind {
+                    Some(SegmentKind::Real) => Address::Real(seg, off),
+                    Some(SegmentKind::Prot) => Address::Prot(seg, off),
+                    None => mode.address(seg, off),
+                }
+            }
+            Self::Far(ptr) => {
+                let ptr = ptr.eval(regs, target)?;
+
+                mode.address((ptr >> 16) as u16, ptr & 0xFFFF)
+            }
+            Self::Linear(e) => Address::Linear(e.eval(regs, target)?),
+            Self::Physical(e) => Address::Physical(e.eval(regs, target)?),
+        })
+    }
+}
+
+/// Parses a hexadecimal number, with an optional `0x` prefix or `h` suffix.
+pub fn parse_number(word: &str) -> Result<u32> {
+    let lower = word.to_ascii_lowercase();
+    let digits = lower
+        .strip_prefix("0x")
+        .or_else(|| lower.strip_suffix('h'))
+        .unwrap_or(&lower);
+
+    u32::from_str_radix(digits, 16).map_err(|_| anyhow!("Invalid number `{word}`."))
+}
+
+/// Hexadecimal number the way `parse_number` and expressions read it back, a leading zero
+/// keeps e.g. `0CF` from being taken for the carry flag.
+pub fn format_number(value: u32) -> String {
+    let text = format!("{value:X}");
+
+    if text.starts_with(|c: char| c.is_ascii_alphabetic()) {
+        format!("0{text}")
+    } else {
+        text
+    }
+}
+
+/// The 32-bit register a part of a register like `ax` or `ah` belongs to, other names as they are.
+pub fn full_register(name: &str) -> &str {
+    match name {
+        "ax" | "ah" | "al" => "eax",
+        "bx" | "bh" | "bl" => "ebx",
+        "cx" | "ch" | "cl" => "ecx",
+        "dx" | "dh" | "dl" => "edx",
+        "si" => "esi",
+        "di" => "edi",
+        "bp" => "ebp",
+        "sp" => "esp",
+        "ip" => "eip",
+        _ => name,
+    }
+}
+
+/// Changes a register or a flag, writing a part of a register like `ah` keeps the rest.
+///
+/// Returns false for unknown names.
+pub fn set_register(regs: &mut Regs, name: &str, value: u32) -> bool {
+    let r = regs;
+    let word = |reg: u32| (reg & 0xFFFF_0000) | (value & 0xFFFF);
+    let low = |reg: u32| (reg & 0xFFFF_FF00) | (value & 0xFF);
+    let high = |reg: u32| (reg & 0xFFFF_00FF) | ((value & 0xFF) << 8);
+
+    match name {
+        "eax" => r.eax = value,
+        "ebx" => r.ebx = value,
+        "ecx" => r.ecx = value,
+        "edx" => r.edx = value,
+        "esi" => r.esi = value,
+        "edi" => r.edi = value,
+        "ebp" => r.ebp = value,
+        "esp" => r.esp = value,
+        "eip" => r.eip = value,
+        "ax" => r.eax = word(r.eax),
+        "bx" => r.ebx = word(r.ebx),
+        "cx" => r.ecx = word(r.ecx),
+        "dx" => r.edx = word(r.edx),
+        "si" => r.esi = word(r.esi),
+        "di" => r.edi = word(r.edi),
+        "bp" => r.ebp = word(r.ebp),
+        "sp" => r.esp = word(r.esp),
+        "ip" => r.eip = word(r.eip),
+        "al" => r.eax = low(r.eax),
+        "bl" => r.ebx = low(r.ebx),
+        "cl" => r.ecx = low(r.ecx),
+        "dl" => r.edx = low(r.edx),
+        "ah" => r.eax = high(r.eax),
+        "bh" => r.ebx = high(r.ebx),
+        "ch" => r.ecx = high(r.ecx),
+        "dh" => r.edx = high(r.edx),
+        "cs" => r.cs = value as u16,
+        "ds" => r.ds = value as u16,
+        "es" => r.es = value as u16,
+        "fs" => r.fs = value as u16,
+        "gs" => r.gs = value as u16,
+        "ss" => r.ss = value as u16,
+        "cf" => r.cf = value != 0,
+        "pf" => r.pf = value != 0,
+        "af" => r.af = value != 0,
+        "zf" => r.zf = value != 0,
+        "sf" => r.sf = value != 0,
+        "tf" => r.tf = value != 0,
+        "if" => r.r#if = value != 0,
+        "df" => r.df = value != 0,
+        "of" => r.of = value != 0,
+        "iopl" => r._iopl = (value & 3) as u8,
+        "nt" => r._nt = value != 0,
+        "vm" => r._vm = value != 0,
+        "ac" => r._ac = value != 0,
+        "id" => r._id = value != 0,
+        "efl" => {
+            let bit = |n: u32| value & (1 << n) != 0;
+
+            r.cf = bit(0);
+            r.pf = bit(2);
+            r.af = bit(4);
+            r.zf = bit(6);
+            r.sf = bit(7);
+            r.tf = bit(8);
+            r.r#if = bit(9);
+            r.df = bit(10);
+            r.of = bit(11);
+            r._iopl = ((value >> 12) & 3) as u8;
+            r._nt = bit(14);
+            r._vm = bit(17);
+            r._ac = bit(18);
+            r._id = bit(21);
+        }
+        _ => return false,
+    }
+
+    true
+}
+
+#[derive(Clone)]
+struct Parser<'a> {
+    text: &'a str,
+    chars: Peekable<CharIndices<'a>>,
+}
+
+impl Parser<'_> {
+    fn skip_spaces(&mut self) {
+        while self.chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+    }
+
+    fn rest(&mut self) -> &str {
+        let pos = self
+            .chars
+            .peek()
+            .map(|(p, _)| *p)
+            .unwrap_or(self.text.len());
+
+        &self.text[pos..]
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_spaces();
+
+        if !self.rest().starts_with(token) {
+            return false;
+        }
+
+        // shifts bind tighter than comparisons, so only these can be confused
+        if matches!(token, "|" | "&") && self.rest()[1..].starts_with(token) {
+            return false;
+        }
+
+        for _ in token.chars() {
+            self.chars.next();
+        }
+
+        true
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(anyhow!("Expected `{token}`."))
+        }
+    }
+
+    fn word(&mut self) -> String {
+        self.skip_spaces();
+
+        let mut word = String::new();
+
+        while let Some((_, c)) = self.chars.next_if(|(_, c)| c.is_ascii_alphanumeric()) {
+            word.push(c.to_ascii_lowercase());
+        }
+
+        word
+    }
+
+    fn binary(&mut self, level: usize) -> Result<Expr> {
+        let Some(ops) = BINARY_OPS.get(level) else {
+            return self.unary();
+        };
+
+        let mut expr = self.binary(level + 1)?;
+
+        'outer: loop {
+            for op in ops.iter() {
+                if self.eat(op) {
+                    let rhs = self.binary(level + 1)?;
+                    expr = Expr::Binary(op, Box::new(expr), Box::new(rhs));
+
+                    continue 'outer;
+                }
+            }
+
+            return Ok(expr);
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        for op in ["-", "!", "~"] {
+            if self.eat(op) {
+                return Ok(Expr::Unary(op, Box::new(self.unary()?)));
+            }
+        }
+
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        if self.eat("(") {
+            let expr = self.binary(0)?;
+            self.expect(")")?;
+
+            return Ok(expr);
+        }
+
+        if self.eat("[") {
+            return self.memory(2);
+        }
+
+        let rest = self.rest();
+
+        match rest.chars().next() {
+            Some(c) if c.is_ascii_digit() => {
+                let word = self.word();
+
+                parse_number(&word).map(Expr::Num)
+            }
+            Some(c) if c.is_ascii_alphabetic() => {
+                let word = self.word();
+                let size = match word.as_str() {
+                    "byte" => Some(1),
+                    "word" => Some(2),
+                    "dword" => Some(4),
+                    _ => None,
+                };
+
+                if let Some(size) = size {
+                    self.expect("[")?;
+
+                    return self.memory(size);
+                }
+
+                // a register name wins over a number spelled the same, e.g. `cf` and `0cf`
+                match REGISTERS.iter().find(|r| **r == word) {
+                    Some(r) => Ok(Expr::Reg(r)),
+                    None => parse_number(&word)
+                        .map(Expr::Num)
+                        .map_err(|_| anyhow!("Unknown register `{word}`.")),
+                }
+            }
+            Some(_) => bail!("Unexpected `{rest}`."),
+            None => bail!("Unexpected end of expression."),
+        }
+    }
+
+    // the opening bracket is already taken
+    fn memory(&mut self, size: u32) -> Result<Expr> {
+        let first = self.binary(0)?;
+
+        let expr = if self.eat(":") {
+            Expr::Mem(size, Some(Box::new(first)), Box::new(self.binary(0)?))
+        } else {
+            Expr::Mem(size, None, Box::new(first))
+        };
+
+        self.expect("]")?;
+
+        Ok(expr)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake::Machine;
+
+    fn eval(text: &str) -> u32 {
+        let machine = Machine::new(&[0x34, 0x12, 0x78, 0x56]);
+        let mut regs = machine.regs().unwrap();
+        regs.esi = 0x100;
+        regs.eax = 0x4C00;
+
+        Expr::parse(text).unwrap().eval(&regs, &machine).unwrap()
+    }
+
+    #[test]
+    fn hex_literals() {
+        assert_eq!(eval("B800"), 0xB800);
+        assert_eq!(eval("ffff"), 0xFFFF);
+        assert_eq!(eval("0B800"), 0xB800);
+        assert_eq!(eval("0x1F"), 0x1F);
+        assert_eq!(eval("1Fh"), 0x1F);
+        assert_eq!(eval("FFFF0000"), 0xFFFF_0000);
+        assert_eq!(eval("0cf"), 0xCF);
+        assert_eq!(eval("cf"), 0);
+        assert_eq!(eval("ah"), 0x4C);
+    }
+
+    #[test]
+    fn system_flags() {
+        assert_eq!(eval("efl & 200"), 0x200);
+        assert_eq!(eval("iopl + nt + vm + id"), 0);
+        assert_eq!(eval("ac"), 0);
+        assert_eq!(eval("0ac"), 0xAC);
+    }
+
+    fn locate(text: &str) -> Result<Address> {
+        let machine = Machine::new(&[]);
+        let mut regs = machine.regs().unwrap();
+        regs.edi = 0x20;
+
+        // the vector of `int 21h`
+        for (i, b) in (0x84..).zip([0x34, 0x12, 0x00, 0xF0]) {
+            machine.write_mem(Address::Real(0, i), b).unwrap();
+        }
+
+        Location::parse(text)?.eval(&regs, &machine, 0x2000)
+    }
+
+    #[test]
+    fn locations() {
+        assert!(locate("b800:0").unwrap() == Address::Real(0xB800, 0));
+        assert!(locate("rm b800:0").unwrap() == Address::Real(0xB800, 0));
+        assert!(locate("pm 0008:00001234").unwrap() == Address::Prot(8, 0x1234));
+        assert!(locate("#12345").unwrap() == Address::Linear(0x12345));
+        assert!(locate("%12345").unwrap() == Address::Physical(0x12345));
+        assert!(locate("#100 * 10").unwrap() == Address::Linear(0x1000));
+        assert!(locate("es:di").unwrap() == Address::Real(0x1000, 0x20));
+        assert!(locate("ss:sp-2").unwrap() == Address::Real(0x1000, 0xFFFC));
+        assert!(locate("far [0:84]").unwrap() == Address::Real(0xF000, 0x1234));
+        assert!(locate("200").unwrap() == Address::Real(0x2000, 0x200));
+
+        assert!(locate("10000:0").is_err());
+        assert!(locate("rm #100").is_err());
+        assert!(locate("b800:").is_err());
+    }
+
+    #[test]
+    fn formatted_numbers_parse_back() {
+        for value in [0, 0xA, 0xAF, 0xCF, 0xDF, 0xB800, 0xFFFF_0000, u32::MAX] {
+            assert_eq!(eval(&format_number(value)), value);
+        }
+    }
+
+    #[test]
+    fn unknown_words() {
+        assert!(Expr::parse("xyz").is_err());
+        assert!(Expr::parse("100000000").is_err());
+        assert!(Expr::parse("ax ==").is_err());
+        assert!(Expr::parse("ax bx").is_err());
+    }
+
+    #[test]
+    fn memory() {
+        assert_eq!(eval("byte [ds:si]"), 0x34);
+        assert_eq!(eval("word [ds:si]"), 0x1234);
+        assert_eq!(eval("dword [si]"), 0x5678_1234);
+        assert_eq!(eval("[1000:si+2]"), 0x5678);
+        assert_eq!(eval("byte [ds:si] == 34 && ax == 4C00"), 1);
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("2 << 1 + 1"), 8);
+        assert_eq!(eval("1 | 2 & 3"), 3);
+        assert_eq!(eval("1 + 1 == 2"), 1);
+        assert_eq!(eval("0 || 1 && 0"), 0);
+        assert_eq!(eval("-1"), u32::MAX);
+        assert_eq!(eval("!0 + ~0"), 0);
+        assert_eq!(eval("ax & FF00 == 4C00"), 1);
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+
+use crate::bus::FpuRegs;
+
+/// Size of a stack register.
+pub const REG_SIZE: usize = 10;
+/// Longest text `real80` makes, e.g. `-1.23456789012345e-4951`.
+pub const REAL80_WIDTH: usize = 23;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tag {
+    Valid,
+    Zero,
+    /// NaN, infinity, denormal or an unsupported format.
+    Special,
+    Empty,
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Valid => "V",
+            Self::Zero => "Z",
+            Self::Special => "S",
+            Self::Empty => "E",
+        })
+    }
+}
+
+impl FpuRegs {
+    /// Physical register ST0 is.
+    pub fn top(&self) -> usize {
+        usize::from((self.sw >> 11) & 7)
+    }
+
+    /// 80-bit value of ST(i), empty if the target sent too little.
+    pub fn st(&self, i: usize) -> &[u8] {
+        let start = (self.top() + i) % 8 * REG_SIZE;
+
+        self.regs.get(start..start + REG_SIZE).unwrap_or_default()
+    }
+
+    pub fn tag(&self, i: usize) -> Tag {
+        match (self.tw >> ((self.top() + i) % 8 * 2)) & 3 {
+            0 => Tag::Valid,
+            1 => Tag::Zero,
+            2 => Tag::Special,
+            _ => Tag::Empty,
+        }
+    }
+}
+
+/// Formats x87 extended precision, the integer bit of the mantissa is explicit there.
+///
+/// The exponent range is much wider than that of `f64`, so the value is worked out
+/// exactly in decimal: `mantissa * 2^k`, or `mantissa * 5^-k / 10^-k` when `k` is
+/// negative.
+pub fn real80(b: &[u8]) -> String {
+    let mantissa = u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]);
+    let exponent = i32::from(u16::from_le_bytes([b[8], b[9]]) & 0x7FFF);
+    let sign = if b[9] & 0x80 != 0 { "-" } else { "" };
+
+    match exponent {
+        0x7FFF if mantissa << 1 == 0 => return format!("{sign}inf"),
+        0x7FFF => return "NaN".to_owned(),
+        _ if mantissa == 0 => return format!("{sign}0.00000000000000e0"),
+        _ => (),
+    }
+
+    // denormals use the exponent of the smallest normal number
+    let shift = exponent.max(1) - 16383 - 63;
+    let (factor, mut count, mut power) = if shift < 0 {
+        (5u32, -shift, shift)
+    } else {
+        (2, shift, 0)
+    };
+    let mut limbs = vec![
+        (mantissa % BASE) as u32,
+        (mantissa / BASE % BASE) as u32,
+        (mantissa / BASE / BASE) as u32,
+    ];
+    while count > 0 {
+        // 5^13 still fits in a u32
+        let k = count.min(13);
+        mul(&mut limbs, factor.pow(k as u32));
+        count -= k;
+    }
+
+    let digits = limbs
+        .iter()
+        .rev()
+        .map(|l| format!("{l:09}"))
+        .collect::<String>();
+    let digits = digits.trim_start_matches('0');
+    let (head, rest) = digits.split_at(digits.len().min(15));
+    let mut lead = head.parse::<u64>().unwrap_or_default() * 10u64.pow(15 - head.len() as u32);
+    power += digits.len() as i32 - 1;
+
+    // round half to even, a carry out of the top digit moves the exponent
+    let up = match rest.as_bytes() {
+        [] => false,
+        [d, tail @ ..] => {
+            *d > b'5' || *d == b'5' && (tail.iter().any(|&d| d != b'0') || lead % 2 == 1)
+        }
+    };
+    if up {
+        lead += 1;
+        if lead == 10u64.pow(15) {
+            lead /= 10;
+            power += 1;
+        }
+    }
+
+    let lead = lead.to_string();
+    format!("{sign}{}.{}e{power}", &lead[..1], &lead[1..])
+}
+
+/// Limb size of the numbers `real80` scales.
+const BASE: u64 = 1_000_000_000;
+
+/// Multiplies a number kept in base 10^9 limbs, the lowest first.
+fn mul(limbs: &mut Vec<u32>, by: u32) {
+    let mut carry = 0;
+    for limb in limbs.iter_mut() {
+        let x = u64::from(*limb) * u64::from(by) + carry;
+        *limb = (x % BASE) as u32;
+        carry = x / BASE;
+    }
+    while carry != 0 {
+        limbs.push((carry % BASE) as u32);
+        carry /= BASE;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn real(mantissa: u64, exponent: u16) -> String {
+        let mut b = mantissa.to_le_bytes().to_vec();
+        b.extend(exponent.to_le_bytes());
+
+        real80(&b)
+    }
+
+    #[test]
+    fn real80_values() {
+        assert_eq!(real(1 << 63, 0x3FFF), "1.00000000000000e0");
+        assert_eq!(real(0xA << 60, 0xC000), "-2.50000000000000e0");
+        assert_eq!(real(0, 0), "0.00000000000000e0");
+        assert_eq!(real(1 << 63, 0x7FFF), "inf");
+        assert_eq!(real(3 << 62, 0x7FFF), "NaN");
+    }
+
+    #[test]
+    fn real80_large_exponents() {
+        // the nearest values to 1e4000 and 1e-3000 and the largest number, digits
+        // beyond f64 precision included
+        assert_eq!(real(0xD1BA_8323_FE55_8C61, 0x73E6), "1.00000000000000e4000");
+        assert_eq!(
+            real(0xB782_C6B6_9696_E166, 0x1911),
+            "1.23456789012345e-3000"
+        );
+        assert_eq!(real(u64::MAX, 0x7FFE), "1.18973149535723e4932");
+    }
+
+    #[test]
+    fn real80_width() {
+        // the smallest denormal and the largest number
+        let longest = [real(1, 0x8000), real(u64::MAX, 0xFFFE), real(1, 0)];
+
+        assert_eq!(longest[0].len(), REAL80_WIDTH);
+        assert!(longest.iter().all(|t| t.len() <= REAL80_WIDTH));
+    }
+}
@@ -1,13 +1,59 @@
+use std::fmt;
+
 pub mod dec;
+pub mod desc;
+pub mod expr;
+pub mod fpu;
+pub mod paging;
 
+/// A place in memory together with the way it becomes a physical address.
 #[derive(Copy, Clone, PartialEq)]
-pub struct Address {
-    pub segment: u16,
-    pub offset: u32,
+pub enum Address {
+    /// `segment:offset` of real or V86 mode.
+    Real(u16, u32),
+    /// `selector:offset`, the selector is looked up in the GDT or LDT.
+    Prot(u16, u32),
+    /// Linear address, paging applies.
+    Linear(u32),
+    /// Physical address, paging doesn't apply.
+    Physical(u32),
+}
+
+impl Address {
+    /// Segment or selector, there is none for linear and physical addresses.
+    pub fn segment(&self) -> Option<u16> {
+        match *self {
+            Self::Real(segment, _) | Self::Prot(segment, _) => Some(segment),
+            Self::Linear(_) | Self::Physical(_) => None,
+        }
+    }
+
+    /// Offset within the segment, or the whole address if there is no segment.
+    pub fn offset(&self) -> u32 {
+        match *self {
+            Self::Real(_, offset) | Self::Prot(_, offset) => offset,
+            Self::Linear(addr) | Self::Physical(addr) => addr,
+        }
+    }
+
+    /// The same kind of address in the same segment.
+    pub fn with_offset(self, offset: u32) -> Self {
+        match self {
+            Self::Real(segment, _) => Self::Real(segment, offset),
+            Self::Prot(segment, _) => Self::Prot(segment, offset),
+            Self::Linear(_) => Self::Linear(offset),
+            Self::Physical(_) => Self::Physical(offset),
+        }
+    }
 }
 
-impl From<(u16, u32)> for Address {
-    fn from((segment, offset): (u16, u32)) -> Self {
-        Self { segment, offset }
+impl fmt::Display for Address {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Real(segment, offset) => write!(f, "{segment:04X}:{offset:04X}"),
+            Self::Prot(selector, offset) => write!(f, "{selector:04X}:{offset:08X}"),
+            Self::Linear(addr) => write!(f, "#{addr:08X}"),
+            Self::Physical(addr) => write!(f, "%{addr:08X}"),
+        }
     }
 }
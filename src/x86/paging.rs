@@ -0,0 +1,127 @@
+use anyhow::{bail, Result};
+
+use super::{
+    desc::{self, Kind},
+    Address,
+};
+use crate::{bus::ControlRegs, target::Target};
+
+pub const CR0_PG: u32 = 1 << 31;
+pub const CR4_PSE: u32 = 1 << 4;
+pub const CR4_PAE: u32 = 1 << 5;
+
+const ENTRY_SIZE: u32 = 4;
+const PAGE_PRESENT: u32 = 1 << 0;
+const PAGE_SIZE: u32 = 1 << 7; // a PDE maps a 4 MiB page itself
+
+/// A page directory or page table entry and the physical address it's read from.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Entry {
+    pub addr: u32,
+    pub value: u32,
+}
+
+impl Entry {
+    pub fn present(&self) -> bool {
+        self.value & PAGE_PRESENT != 0
+    }
+
+    /// Physical address of the page table or of the page.
+    pub fn frame(&self) -> u32 {
+        self.value & !0xFFF
+    }
+
+    /// One letter per flag from bit 0 up: present, writable, user, write-through,
+    /// cache disabled, accessed, dirty, page size, global.
+    pub fn flags(&self) -> String {
+        "PWUTCADSG"
+            .chars()
+            .enumerate()
+            .map(|(i, c)| if self.value & (1 << i) != 0 { c } else { '-' })
+            .collect()
+    }
+}
+
+/// Steps of the translation of a linear address, there are none with paging off.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Walk {
+    pub linear: u32,
+    pub pde: Option<Entry>,
+    /// Missing for a 4 MiB page or when the PDE isn't present.
+    pub pte: Option<Entry>,
+    /// Missing when the page isn't present.
+    pub physical: Option<u32>,
+}
+
+/// Linear address of a place in memory, a physical one has none.
+pub fn linear(target: &dyn Target, addr: Address) -> Result<Option<u32>> {
+    Ok(Some(match addr {
+        Address::Real(segment, offset) => (u32::from(segment) << 4).wrapping_add(offset),
+        Address::Prot(selector, offset) => segment_base(target, selector)?.wrapping_add(offset),
+        Address::Linear(addr) => addr,
+        Address::Physical(_) => return Ok(None),
+    }))
+}
+
+/// Base of the segment a selector refers to in the GDT or the LDT.
+fn segment_base(target: &dyn Target, selector: u16) -> Result<u32> {
+    match desc::lookup(target, selector)?.kind {
+        Kind::Code { base, .. } | Kind::Data { base, .. } | Kind::System { base, .. } => Ok(base),
+        _ => bail!("Selector {selector:04X} isn't a segment."),
+    }
+}
+
+/// Walks the page directory and the page table the way the CPU does.
+pub fn walk(target: &dyn Target, cr: &ControlRegs, linear: u32) -> Result<Walk> {
+    let mut walk = Walk {
+        linear,
+        pde: None,
+        pte: None,
+        physical: None,
+    };
+
+    if cr.cr0 & CR0_PG == 0 {
+        walk.physical = Some(linear);
+        return Ok(walk);
+    }
+
+    if cr.cr4 & CR4_PAE != 0 {
+        bail!("PAE paging isn't supported.");
+    }
+
+    let pde = read_entry(target, (cr.cr3 & !0xFFF) + (linear >> 22) * ENTRY_SIZE)?;
+    walk.pde = Some(pde);
+
+    if !pde.present() {
+        return Ok(walk);
+    }
+
+    if pde.value & PAGE_SIZE != 0 && cr.cr4 & CR4_PSE != 0 {
+        walk.physical = Some((pde.value & 0xFFC0_0000) | (linear & 0x3F_FFFF));
+        return Ok(walk);
+    }
+
+    let pte = read_entry(target, pde.frame() + ((linear >> 12) & 0x3FF) * ENTRY_SIZE)?;
+    walk.pte = Some(pte);
+
+    if pte.present() {
+        walk.physical = Some(pte.frame() | (linear & 0xFFF));
+    }
+
+    Ok(walk)
+}
+
+fn read_entry(target: &dyn Target, addr: u32) -> Result<Entry> {
+    let data = target.read_mem(Address::Physical(addr), ENTRY_SIZE)?;
+
+    match data.try_into() {
+        Ok(b) => Ok(Entry {
+            addr,
+            value: u32::from_le_bytes(b),
+        }),
+        Err(_) => bail!(
+            "Paging entry at {} is out of memory.",
+            Address::Physical(addr)
+        ),
+    }
+}
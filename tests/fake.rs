@@ -0,0 +1,320 @@
+//! The D-Bus proxy and the decoder against the fake DOSBox, served on a private bus.
+
+use std::{
+    io::{BufRead, BufReader},
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    time::Duration,
+};
+use zbus::blocking::{Connection, ConnectionBuilder};
+
+use debugbox::{
+    breakpoints::{Breakpoints, Condition, Kind},
+    bus::Proxy,
+    fake::{self, Machine},
+    target::Target,
+    x86::{
+        dec::{fetch_after, step_over},
+        Address,
+    },
+};
+
+const BREAK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `dbus-daemon` of its own for every test, so they don't see each other's `com.dosbox`.
+struct Bus {
+    daemon: Child,
+    address: String,
+}
+
+impl Bus {
+    fn start() -> Self {
+        let mut daemon = Command::new("dbus-daemon")
+            .args(["--session", "--nofork", "--print-address"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("dbus-daemon is needed to run the tests");
+
+        let mut address = String::new();
+        BufReader::new(daemon.stdout.take().unwrap())
+            .read_line(&mut address)
+            .unwrap();
+
+        Self {
+            daemon,
+            address: address.trim().to_owned(),
+        }
+    }
+
+    fn connect(&self) -> ConnectionBuilder<'static> {
+        ConnectionBuilder::address(self.address.as_str()).unwrap()
+    }
+}
+
+impl Drop for Bus {
+    fn drop(&mut self) {
+        let _ = self.daemon.kill();
+        let _ = self.daemon.wait();
+    }
+}
+
+/// The fake with `code` at `1000:0100` and a proxy talking to it.
+struct Session {
+    proxy: Proxy,
+    _server: Connection,
+    _bus: Bus,
+}
+
+impl Session {
+    fn start(code: &[u8]) -> Self {
+        Self::with(Machine::new(code))
+    }
+
+    fn with(machine: Machine) -> Self {
+        let bus = Bus::start();
+        let server = fake::serve_on(bus.connect(), machine).unwrap();
+        let proxy = Proxy::new(&bus.connect().build().unwrap()).unwrap();
+
+        Self {
+            proxy,
+            _server: server,
+            _bus: bus,
+        }
+    }
+
+    fn eip(&self) -> u32 {
+        self.proxy.regs().unwrap().eip
+    }
+}
+
+fn at(offset: u32) -> Address {
+    Address::Real(0x1000, offset)
+}
+
+#[test]
+fn step_in_moves_past_the_instruction() {
+    // mov ax, 1234h / nop
+    let s = Session::start(&[0xB8, 0x34, 0x12, 0x90]);
+
+    assert_eq!(s.eip(), 0x100);
+    assert_eq!(s.proxy.step_in().unwrap(), 0x103);
+    assert_eq!(s.eip(), 0x103);
+    assert_eq!(s.proxy.step_in().unwrap(), 0x104);
+}
+
+#[test]
+fn run_stops_at_int3_and_announces_it() {
+    // nop / nop / int3
+    let s = Session::start(&[0x90, 0x90, 0xCC]);
+    let (tx, rx) = mpsc::channel();
+
+    s.proxy
+        .on_break(Box::new(move || tx.send(()).unwrap()))
+        .unwrap();
+
+    assert_eq!(s.proxy.run().unwrap(), 0x100);
+    rx.recv_timeout(BREAK_TIMEOUT).unwrap();
+    assert_eq!(s.eip(), 0x102);
+}
+
+#[test]
+fn code_breakpoint_add_hit_remove() {
+    // four nops and int3
+    let s = Session::start(&[0x90, 0x90, 0x90, 0x90, 0xCC]);
+    let mut bps = Breakpoints::default();
+
+    bps.add(&s.proxy, Kind::Code(at(0x102)), false).unwrap();
+    assert!(bps.add(&s.proxy, Kind::Code(at(0x102)), false).is_err());
+    assert!(s
+        .proxy
+        .add_breakpoint(Kind::Code(at(0x102)), false)
+        .is_err());
+
+    s.proxy.run().unwrap();
+    let regs = s.proxy.regs().unwrap();
+    assert_eq!(regs.eip, 0x102);
+    assert!(bps.hit(&s.proxy, &regs, None).unwrap());
+    assert_eq!(bps.get(at(0x102)).unwrap().hits, 1);
+
+    bps.remove(&s.proxy, 0).unwrap();
+    assert!(bps.is_empty());
+
+    s.proxy.run().unwrap();
+    assert_eq!(s.eip(), 0x104);
+}
+
+#[test]
+fn breakpoint_in_a_loop_stops_every_pass() {
+    // nop / L: nop / loop L / int3
+    let s = Session::start(&[0x90, 0x90, 0xE2, 0xFD, 0xCC]);
+
+    s.proxy
+        .add_breakpoint(Kind::Code(at(0x101)), false)
+        .unwrap();
+
+    // CX starts at 0, so the loop goes round 65536 times
+    for cx in [0, 0xFFFF] {
+        s.proxy.run().unwrap();
+        let regs = s.proxy.regs().unwrap();
+        assert_eq!((regs.eip, regs.ecx), (0x101, cx));
+    }
+}
+
+#[test]
+fn conditional_breakpoint_is_let_through() {
+    // nop / int3
+    let s = Session::start(&[0x90, 0xCC]);
+    let mut bps = Breakpoints::default();
+
+    bps.add(&s.proxy, Kind::Code(at(0x101)), false).unwrap();
+    bps.set_condition(0, Some(Condition::parse("ax == 4C00").unwrap()));
+
+    s.proxy.run().unwrap();
+    let mut regs = s.proxy.regs().unwrap();
+    assert!(!bps.hit(&s.proxy, &regs, None).unwrap());
+
+    regs.eax = 0x4C00;
+    assert!(bps.hit(&s.proxy, &regs, None).unwrap());
+}
+
+#[test]
+fn conditional_breakpoint_stops_on_a_later_pass() {
+    // nop / L: nop / loop L / int3
+    let s = Session::start(&[0x90, 0x90, 0xE2, 0xFD, 0xCC]);
+    let mut bps = Breakpoints::default();
+
+    bps.add(&s.proxy, Kind::Code(at(0x101)), false).unwrap();
+    bps.set_condition(0, Some(Condition::parse("cx == FFFF").unwrap()));
+
+    s.proxy.run().unwrap();
+    let regs = s.proxy.regs().unwrap();
+    assert_eq!((regs.eip, regs.ecx), (0x101, 0));
+    assert!(!bps.hit(&s.proxy, &regs, None).unwrap());
+
+    // let through like the UI does, the breakpoint still stops the next pass
+    s.proxy.run().unwrap();
+    let regs = s.proxy.regs().unwrap();
+    assert_eq!((regs.eip, regs.ecx), (0x101, 0xFFFF));
+    assert!(bps.hit(&s.proxy, &regs, None).unwrap());
+}
+
+#[test]
+fn int_breakpoint_filters_ah() {
+    // AH is 0, and the `int` at CS:IP is never reported, so only the second one can stop
+    for (ah, eip) in [(0x4C, 0x104), (0, 0x102)] {
+        // int 21h / int 21h / int3
+        let s = Session::start(&[0xCD, 0x21, 0xCD, 0x21, 0xCC]);
+
+        s.proxy
+            .add_breakpoint(
+                Kind::Int {
+                    num: 0x21,
+                    ah: Some(ah),
+                },
+                false,
+            )
+            .unwrap();
+
+        s.proxy.run().unwrap();
+        assert_eq!(s.eip(), eip);
+    }
+}
+
+#[test]
+fn run_from_an_int_breakpoint() {
+    // nop / L: int 21h / loop L / int3
+    let s = Session::start(&[0x90, 0xCD, 0x21, 0xE2, 0xFC, 0xCC]);
+
+    s.proxy
+        .add_breakpoint(
+            Kind::Int {
+                num: 0x21,
+                ah: None,
+            },
+            false,
+        )
+        .unwrap();
+
+    s.proxy.run().unwrap();
+    let regs = s.proxy.regs().unwrap();
+    assert_eq!((regs.eip, regs.ecx), (0x101, 0));
+
+    // not the same `int` again right away, but the next pass
+    s.proxy.run().unwrap();
+    let regs = s.proxy.regs().unwrap();
+    assert_eq!((regs.eip, regs.ecx), (0x101, 0xFFFF));
+}
+
+#[test]
+fn watchpoint_reports_the_change() {
+    let s = Session::start(&[0x90, 0x90, 0x90, 0xCC]);
+    let kind = Kind::Write(at(0x200));
+
+    s.proxy.add_breakpoint(kind, false).unwrap();
+    s.proxy.write_mem(at(0x200), 0x55).unwrap();
+    s.proxy.run().unwrap();
+
+    let hit = s.proxy.watch_hit().unwrap().unwrap();
+    assert!(hit.kind == kind);
+    assert_eq!((hit.old, hit.new), (0, 0x55));
+    assert!(hit.writer == at(0x100));
+}
+
+#[test]
+fn memory_read_and_write() {
+    let s = Session::start(&[0x12, 0x34]);
+
+    assert_eq!(s.proxy.read_mem(at(0x100), 3).unwrap(), [0x12, 0x34, 0]);
+    assert_eq!(s.proxy.write_mem(at(0x101), 0xAB).unwrap(), 0x34);
+    assert_eq!(s.proxy.read_mem(at(0x100), 2).unwrap(), [0x12, 0xAB]);
+
+    // the same bytes by their linear and physical addresses
+    assert_eq!(
+        s.proxy.read_mem(Address::Linear(0x10100), 2).unwrap(),
+        [0x12, 0xAB]
+    );
+    assert_eq!(
+        s.proxy.read_mem(Address::Physical(0x10101), 1).unwrap(),
+        [0xAB]
+    );
+
+    assert!(s.proxy.read_mem(Address::Prot(8, 0), 1).is_err());
+
+    // no more than a segment at once
+    assert_eq!(s.proxy.read_mem(at(0), 0x20000).unwrap().len(), 0x10000);
+}
+
+#[test]
+fn step_over_a_call() {
+    // call 0108 / nop / int3
+    let s = Session::start(&[0xE8, 0x05, 0x00, 0x90, 0xCC]);
+
+    let code = fetch_after(&s.proxy, at(0x100), 2).unwrap();
+    assert!(code[0].0.is_call_near());
+
+    let trap = step_over(&s.proxy, at(0x100)).unwrap().unwrap();
+    let regs = s.proxy.regs().unwrap();
+
+    assert_eq!(regs.eip, 0x103);
+    assert!(trap.reached(&regs));
+
+    trap.restore(&s.proxy).unwrap();
+    assert_eq!(s.proxy.read_mem(at(0x103), 1).unwrap(), [0x90]);
+
+    // anything else is a single step
+    assert!(step_over(&s.proxy, at(0x103)).unwrap().is_none());
+    assert_eq!(s.eip(), 0x104);
+}
+
+#[test]
+fn descriptor_lookup() {
+    let machine = Machine::new(&[]);
+    // null, flat 32-bit code
+    machine.load_gdt(&[[0; 8], 0x00CF_9A00_0000_FFFFu64.to_le_bytes()].concat());
+    let s = Session::with(machine);
+
+    assert_eq!(s.proxy.descriptor(8).unwrap(), 0x00CF_9A00_0000_FFFF);
+    assert!(s.proxy.descriptor(0x10).is_err());
+    assert!(s.proxy.descriptor(0xC).is_err());
+}